@@ -1,38 +1,26 @@
 pub mod adjusted_color {
+    use crate::color::color::scale_light;
+    use crate::theme::theme::resolve;
     use anyhow::Result;
-    use chrono::Local;
-    use palette::{Darken, IntoColor, Srgb};
+    use palette::{FromColor, LinSrgb, Oklab, Srgb};
     use raqote::{SolidSource, Source};
-    use std::str::FromStr;
-    use suncalc::Timestamp;
-
-    pub fn get_sun_darkening() -> f64 {
-        let lat = 40.692778;
-        let lon = -73.990278;
-        let sun_pos = suncalc::get_position(Timestamp(Local::now().timestamp()), lat, lon);
-
-        if sun_pos.altitude < 0.0 {
-            // If the sun is down, dark this a lot
-            0.8
-        } else {
-            // Otherwise, slightly darken throughout the day.
-            (90.0 - sun_pos.altitude) / 180.0
-        }
-    }
 
     pub fn adjusted_color_with_tint(hex: &str, tint: f64) -> Result<raqote::Source<'static>> {
-        let mut color = Srgb::from_str(&hex)?.into_linear();
+        let color = resolve(hex)?.into_format::<f32>().into_linear();
 
-        color = color.darken(get_sun_darkening() + tint);
+        // Darken in OKLab, so the hue holds. The time of day is applied
+        // to whole frames for each device, on top of this, by its
+        // brightness policy and calibration.
+        let color = scale_light(Oklab::from_color(color), (1.0 - tint) as f32);
 
         let Srgb {
             standard: _,
             red,
             green,
             blue,
-        } = color.into_color();
+        } = Srgb::<f32>::from_linear(LinSrgb::from_color(color));
 
-        color_to_source(red, green, blue)
+        color_to_source(red as f64, green as f64, blue as f64)
     }
 
     pub fn adjusted_color(hex: &str) -> Result<raqote::Source<'static>> {
@@ -42,9 +30,9 @@ pub mod adjusted_color {
     pub fn color_to_source(red: f64, green: f64, blue: f64) -> Result<raqote::Source<'static>> {
         Ok(Source::Solid(SolidSource::from_unpremultiplied_argb(
             255,
-            (red * 255.0).floor() as u8,
-            (green * 255.0).floor() as u8,
-            (blue * 255.0).floor() as u8,
+            (red.clamp(0.0, 1.0) * 255.0).round() as u8,
+            (green.clamp(0.0, 1.0) * 255.0).round() as u8,
+            (blue.clamp(0.0, 1.0) * 255.0).round() as u8,
         )))
     }
 }
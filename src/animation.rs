@@ -0,0 +1,170 @@
+/// Encoding rendered frames into an animated WebP.
+pub mod animation {
+    use anyhow::{anyhow, Result};
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use webp::{AnimEncoder, AnimFrame, WebPConfig};
+
+    /// One rendered frame, as RGBA bytes, and how long it shows for in ms.
+    #[derive(Clone)]
+    pub struct Frame {
+        pub rgba: Vec<u8>,
+        /// How much light each pixel keeps, from 0 to 1, for stale
+        /// widgets. Calibration applies it before rounding.
+        pub light: Vec<f32>,
+        pub duration: u32,
+    }
+
+    /// Loop `frames` until they fill `duration` ms, cutting the
+    /// last one short so that the total comes out exact.
+    pub fn repeat_for(frames: &[Frame], duration: u32) -> Vec<Frame> {
+        if frames.iter().map(|frame| frame.duration).sum::<u32>() == 0 {
+            return frames.to_vec();
+        }
+        if let [frame] = frames {
+            // Nothing moves, so hold the one frame.
+            return vec![Frame {
+                rgba: frame.rgba.clone(),
+                light: frame.light.clone(),
+                duration,
+            }];
+        }
+        let mut repeated = Vec::new();
+        let mut elapsed = 0;
+        for frame in frames.iter().cycle() {
+            if elapsed >= duration {
+                break;
+            }
+            let shown = frame.duration.min(duration - elapsed);
+            repeated.push(Frame {
+                rgba: frame.rgba.clone(),
+                light: frame.light.clone(),
+                duration: shown,
+            });
+            elapsed += shown;
+        }
+        repeated
+    }
+
+    /// A hash of the frames, their dimming, how long each shows and
+    /// the brightness level they're shown at, to tell whether anything
+    /// has changed since the last push.
+    pub fn fingerprint(frames: &[Frame], level: f64) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        level.to_bits().hash(&mut hasher);
+        for frame in frames {
+            frame.rgba.hash(&mut hasher);
+            for light in &frame.light {
+                light.to_bits().hash(&mut hasher);
+            }
+            frame.duration.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// The offset of the duration in an ANMF chunk's payload,
+    /// after the 24-bit x, y, width and height fields.
+    const ANMF_DURATION_OFFSET: usize = 12;
+
+    /// libwebp works out the last frame's duration from the timestamp of
+    /// a final empty frame, which the webp crate always passes as 0. With
+    /// real timestamps, libwebp rejects that and uses the average duration
+    /// instead. So rewrite the last ANMF chunk to make the total come out
+    /// right. Identical frames may have been merged, which is why this
+    /// fixes up the total rather than setting the duration directly.
+    fn set_total_duration(webp: &mut [u8], total: u32) {
+        let mut chunks: Vec<(usize, u32)> = Vec::new();
+        // Skip "RIFF", the file size and "WEBP".
+        let mut offset = 12;
+        while offset + 8 <= webp.len() {
+            let size = u32::from_le_bytes([
+                webp[offset + 4],
+                webp[offset + 5],
+                webp[offset + 6],
+                webp[offset + 7],
+            ]) as usize;
+            let payload = offset + 8;
+            if &webp[offset..offset + 4] == b"ANMF" && payload + 16 <= webp.len() {
+                let at = payload + ANMF_DURATION_OFFSET;
+                let duration = u32::from_le_bytes([webp[at], webp[at + 1], webp[at + 2], 0]);
+                chunks.push((at, duration));
+            }
+            // Chunks are padded to an even length.
+            offset = payload + size + (size & 1);
+        }
+        if let Some((at, duration)) = chunks.last() {
+            let others: u32 = chunks[..chunks.len() - 1].iter().map(|c| c.1).sum();
+            if total > others {
+                let fixed = (total - others).min(0xff_ffff);
+                webp[*at..*at + 3].copy_from_slice(&fixed.to_le_bytes()[..3]);
+            } else {
+                println!("Could not fix last frame duration {}", duration);
+            }
+        }
+    }
+
+    /// Encode frames into a lossless animated WebP, with each frame's
+    /// timestamp being the sum of the durations before it. A
+    /// `loop_count` of 0 loops forever.
+    pub fn encode(frames: &[Frame], width: u32, height: u32, loop_count: i32) -> Result<Vec<u8>> {
+        let mut config = WebPConfig::new().map_err(|_s| anyhow!("WebPConfig failed"))?;
+        config.lossless = 1;
+        let mut encoder = AnimEncoder::new(width, height, &config);
+        encoder.set_loop_count(loop_count);
+
+        let mut timestamp = 0;
+        for frame in frames {
+            encoder.add_frame(AnimFrame::from_rgba(
+                &frame.rgba,
+                width,
+                height,
+                timestamp as i32,
+            ));
+            timestamp += frame.duration;
+        }
+
+        let mut file_contents = encoder
+            .try_encode()
+            .map_err(|e| anyhow!("Could not encode animation: {:?}", e))?
+            .to_vec();
+        if frames.len() > 1 {
+            set_total_duration(&mut file_contents, timestamp);
+        }
+        Ok(file_contents)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::brightness::brightness::quantize;
+
+        fn frames() -> Vec<Frame> {
+            vec![Frame {
+                rgba: vec![255; 16],
+                light: vec![1.0; 4],
+                duration: 100,
+            }]
+        }
+
+        #[test]
+        fn brightness_within_a_step_keeps_the_fingerprint() {
+            let at = |level: f64| fingerprint(&frames(), quantize(level));
+            assert_eq!(at(0.500), at(0.509));
+            assert_eq!(at(0.500), at(0.491));
+            assert_ne!(at(0.50), at(0.52));
+        }
+
+        #[test]
+        fn the_frames_change_the_fingerprint() {
+            let mut changed = frames();
+            changed[0].rgba[0] = 0;
+            assert_ne!(fingerprint(&frames(), 1.0), fingerprint(&changed, 1.0));
+            changed = frames();
+            changed[0].duration = 200;
+            assert_ne!(fingerprint(&frames(), 1.0), fingerprint(&changed, 1.0));
+            changed = frames();
+            changed[0].light[0] = 0.4;
+            assert_ne!(fingerprint(&frames(), 1.0), fingerprint(&changed, 1.0));
+        }
+    }
+}
@@ -1,23 +1,34 @@
 /// Get AQI information from AirNow.
 pub mod aqi {
+    use crate::font::font::FontName;
+    use crate::location::location::{LocationError, Resolver};
+    use crate::source::source::{DataSource, Reading};
     use crate::TextWidget;
-    use anyhow::anyhow;
-    use anyhow::{Context, Result};
-    use cached::proc_macro::cached;
+    use async_trait::async_trait;
+    use chrono::Duration;
     use reqwest::header::USER_AGENT;
-    use serde::Deserialize;
+    use serde::{Deserialize, Serialize};
+    use std::sync::Arc;
+    use thiserror::Error;
 
     #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
     struct Monitor {
-        aqi: Vec<f64>,
-        // conc: Vec<f64>,
-        // parameterName: String,
+        parameter_name: String,
+        /// Hourly, oldest first, with gaps as nulls.
+        #[serde(default)]
+        conc: Vec<Option<f64>>,
+        #[serde(default)]
+        conc_unit: String,
+        /// AirNow's own index for each hour, for pollutants
+        /// whose concentrations can't be used.
+        #[serde(default)]
+        aqi: Vec<Option<f64>>,
         // parameterDisplayName: String,
-        // concUnit: String,
     }
 
     #[derive(Debug, Deserialize)]
-    struct Location {
+    struct Site {
         monitors: Vec<Monitor>,
         // coordinates: Vec<f64>,
         // siteName: String,
@@ -31,40 +42,592 @@ pub mod aqi {
         // fileWrittenDateTime: String,
     }
 
-    #[cached(time = 120, result = true)]
-    pub async fn get_aqi() -> Result<TextWidget> {
-        println!("Recomputing weather");
-
-        // This is hardcoded to Brooklyn. To point
-        // it somewhere else, you'll need to look up
-        // a different weather station.
-        let resp: Location = reqwest::Client::new()
-            .get("https://an_gov_data.s3.amazonaws.com/Sites/360470118.json")
-            .header(USER_AGENT, "tidbyt")
-            .send()
-            .await?
-            .json::<Location>()
-            .await?;
-
-        let mon = resp.monitors[0]
-            .aqi
-            .last()
-            .context("Could not get monitor")?;
-
-        let aqi = *mon;
-
-        let aqi_range = (aqi / 10.0).floor() as u64;
-        if aqi > 100.0 {
-            Ok(TextWidget {
-                text: format!("{aqi_range} AQI"),
-                color: String::from(match aqi_range {
-                    0..=4 => "#92dd67",
-                    5..=10 => "#ffb537",
-                    _ => "#ff3838",
-                }),
-            })
-        } else {
-            Err(anyhow!("AQI Normal"))
+    /// A concentration range and the index range it maps to.
+    type Breakpoint = (f64, f64, u32, u32);
+
+    /// The pollutants the EPA computes an index for.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Pollutant {
+        Pm25,
+        Pm10,
+        Ozone,
+        Co,
+        So2,
+        No2,
+    }
+
+    impl Pollutant {
+        fn from_parameter(name: &str) -> Option<Pollutant> {
+            match name.to_ascii_uppercase().as_str() {
+                "PM2.5" => Some(Pollutant::Pm25),
+                "PM10" => Some(Pollutant::Pm10),
+                "OZONE" | "O3" => Some(Pollutant::Ozone),
+                "CO" => Some(Pollutant::Co),
+                "SO2" => Some(Pollutant::So2),
+                "NO2" => Some(Pollutant::No2),
+                _ => None,
+            }
+        }
+
+        pub fn label(&self) -> &'static str {
+            match self {
+                Pollutant::Pm25 => "PM2.5",
+                Pollutant::Pm10 => "PM10",
+                Pollutant::Ozone => "O3",
+                Pollutant::Co => "CO",
+                Pollutant::So2 => "SO2",
+                Pollutant::No2 => "NO2",
+            }
+        }
+
+        /// The unit the breakpoints are in, and how many decimals
+        /// concentrations are truncated to before looking them up.
+        fn unit(&self) -> (&'static str, i32) {
+            match self {
+                Pollutant::Pm25 => ("UG/M3", 1),
+                Pollutant::Pm10 => ("UG/M3", 0),
+                Pollutant::Ozone => ("PPM", 3),
+                Pollutant::Co => ("PPM", 1),
+                Pollutant::So2 | Pollutant::No2 => ("PPB", 0),
+            }
+        }
+
+        /// The EPA's breakpoints, as revised in 2024, for the averaging
+        /// period that `average` uses.
+        fn breakpoints(&self) -> &'static [Breakpoint] {
+            match self {
+                Pollutant::Pm25 => &[
+                    (0.0, 9.0, 0, 50),
+                    (9.1, 35.4, 51, 100),
+                    (35.5, 55.4, 101, 150),
+                    (55.5, 125.4, 151, 200),
+                    (125.5, 225.4, 201, 300),
+                    (225.5, 325.4, 301, 500),
+                ],
+                Pollutant::Pm10 => &[
+                    (0.0, 54.0, 0, 50),
+                    (55.0, 154.0, 51, 100),
+                    (155.0, 254.0, 101, 150),
+                    (255.0, 354.0, 151, 200),
+                    (355.0, 424.0, 201, 300),
+                    (425.0, 604.0, 301, 500),
+                ],
+                // Eight hours. Above 0.200 only the hourly table applies.
+                Pollutant::Ozone => &[
+                    (0.0, 0.054, 0, 50),
+                    (0.055, 0.070, 51, 100),
+                    (0.071, 0.085, 101, 150),
+                    (0.086, 0.105, 151, 200),
+                    (0.106, 0.200, 201, 300),
+                ],
+                Pollutant::Co => &[
+                    (0.0, 4.4, 0, 50),
+                    (4.5, 9.4, 51, 100),
+                    (9.5, 12.4, 101, 150),
+                    (12.5, 15.4, 151, 200),
+                    (15.5, 30.4, 201, 300),
+                    (30.5, 50.4, 301, 500),
+                ],
+                Pollutant::So2 => &[
+                    (0.0, 35.0, 0, 50),
+                    (36.0, 75.0, 51, 100),
+                    (76.0, 185.0, 101, 150),
+                    (186.0, 304.0, 151, 200),
+                    (305.0, 604.0, 201, 300),
+                    (605.0, 1004.0, 301, 500),
+                ],
+                Pollutant::No2 => &[
+                    (0.0, 53.0, 0, 50),
+                    (54.0, 100.0, 51, 100),
+                    (101.0, 360.0, 101, 150),
+                    (361.0, 649.0, 151, 200),
+                    (650.0, 1249.0, 201, 300),
+                    (1250.0, 2049.0, 301, 500),
+                ],
+            }
+        }
+
+        /// The hourly ozone table, which only applies from 0.125 ppm.
+        const OZONE_HOURLY: [Breakpoint; 4] = [
+            (0.125, 0.164, 101, 150),
+            (0.165, 0.204, 151, 200),
+            (0.205, 0.404, 201, 300),
+            (0.405, 0.604, 301, 500),
+        ];
+
+        /// The index for hourly concentrations in the breakpoints'
+        /// unit, oldest first, averaged the way the EPA does for the
+        /// pollutant: a NowCast for particles, eight hours for ozone
+        /// and CO, and the latest hour for the rest.
+        fn index(&self, hourly: &[Option<f64>]) -> Option<u32> {
+            let latest = hourly.last().copied().flatten();
+            match self {
+                Pollutant::Pm25 | Pollutant::Pm10 => {
+                    lookup(self.truncate(now_cast(hourly)?), self.breakpoints())
+                }
+                Pollutant::Ozone => {
+                    let hourly_index = latest
+                        .filter(|concentration| *concentration >= 0.125)
+                        .and_then(|concentration| {
+                            lookup(self.truncate(concentration), &Pollutant::OZONE_HOURLY)
+                        });
+                    let eight_hour = average(hourly, 8)
+                        .filter(|concentration| *concentration <= 0.200)
+                        .and_then(|concentration| {
+                            lookup(self.truncate(concentration), self.breakpoints())
+                        });
+                    hourly_index.max(eight_hour)
+                }
+                Pollutant::Co => {
+                    let concentration = average(hourly, 8).or(latest)?;
+                    lookup(self.truncate(concentration), self.breakpoints())
+                }
+                Pollutant::So2 | Pollutant::No2 => {
+                    lookup(self.truncate(latest?), self.breakpoints())
+                }
+            }
+        }
+
+        /// Averages land just under round numbers, as 9.1 does at
+        /// 9.0999..., so allow for that before cutting off digits.
+        fn truncate(&self, concentration: f64) -> f64 {
+            let scale = 10f64.powi(self.unit().1);
+            (concentration * scale + 1e-6).floor() / scale
+        }
+    }
+
+    /// Linear interpolation within the breakpoint the concentration
+    /// falls in, capped at the top of the scale.
+    fn lookup(concentration: f64, breakpoints: &[Breakpoint]) -> Option<u32> {
+        if concentration < 0.0 {
+            return None;
+        }
+        let top = breakpoints.last()?;
+        if concentration > top.1 {
+            return Some(top.3);
+        }
+        // Truncated values can fall between one range's top and the
+        // next one's bottom, so take the first range that isn't below.
+        let &(low, high, index_low, index_high) = breakpoints
+            .iter()
+            .find(|(_, high, _, _)| concentration <= *high)?;
+        let fraction = ((concentration - low) / (high - low)).max(0.0);
+        Some((index_low as f64 + fraction * (index_high - index_low) as f64).round() as u32)
+    }
+
+    /// The mean of the last `hours` readings, if at least three
+    /// quarters of them are there.
+    fn average(hourly: &[Option<f64>], hours: usize) -> Option<f64> {
+        let recent: Vec<f64> = hourly.iter().rev().take(hours).flatten().copied().collect();
+        (recent.len() * 4 >= hours * 3).then(|| recent.iter().sum::<f64>() / recent.len() as f64)
+    }
+
+    /// The EPA's NowCast for particles: a weighted average of the last
+    /// twelve hours that leans on recent hours more as the air changes
+    /// faster. It needs two of the last three hours.
+    fn now_cast(hourly: &[Option<f64>]) -> Option<f64> {
+        let recent: Vec<Option<f64>> = hourly.iter().rev().take(12).copied().collect();
+        if recent.iter().take(3).flatten().count() < 2 {
+            return None;
+        }
+        let present = recent.iter().flatten();
+        let (min, max) = present.fold((f64::MAX, f64::MIN), |(min, max), c| {
+            (min.min(*c), max.max(*c))
+        });
+        let weight = if max > 0.0 { (min / max).max(0.5) } else { 1.0 };
+        let (mut sum, mut weights) = (0.0, 0.0);
+        for (hours_ago, concentration) in recent.iter().enumerate() {
+            if let Some(concentration) = concentration {
+                let factor = weight.powi(hours_ago as i32);
+                sum += factor * concentration;
+                weights += factor;
+            }
+        }
+        Some(sum / weights)
+    }
+
+    impl Monitor {
+        /// The monitor's pollutant and index, from its concentrations
+        /// when their unit makes sense, or else AirNow's own index.
+        fn index(&self) -> Option<(Pollutant, u32)> {
+            let pollutant = Pollutant::from_parameter(&self.parameter_name)?;
+            let scale = match (
+                self.conc_unit.to_ascii_uppercase().as_str(),
+                pollutant.unit().0,
+            ) {
+                (from, to) if from == to => Some(1.0),
+                ("PPB", "PPM") => Some(0.001),
+                ("PPM", "PPB") => Some(1000.0),
+                _ => None,
+            };
+            // AirNow marks missing hours with negative values.
+            let valid = |value: &Option<f64>| value.filter(|value| *value >= 0.0);
+            let computed = scale.and_then(|scale| {
+                let hourly: Vec<Option<f64>> = self
+                    .conc
+                    .iter()
+                    .map(|concentration| valid(concentration).map(|c| c * scale))
+                    .collect();
+                pollutant.index(&hourly)
+            });
+            let reported = || {
+                self.aqi
+                    .iter()
+                    .rev()
+                    .find_map(valid)
+                    .map(|aqi| aqi.round() as u32)
+            };
+            Some((pollutant, computed.or_else(reported)?))
+        }
+    }
+
+    /// The EPA's categories, each with its official color.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Category {
+        Good,
+        Moderate,
+        UnhealthyForSensitiveGroups,
+        Unhealthy,
+        VeryUnhealthy,
+        Hazardous,
+    }
+
+    impl Category {
+        pub fn from_index(aqi: u32) -> Category {
+            match aqi {
+                0..=50 => Category::Good,
+                51..=100 => Category::Moderate,
+                101..=150 => Category::UnhealthyForSensitiveGroups,
+                151..=200 => Category::Unhealthy,
+                201..=300 => Category::VeryUnhealthy,
+                _ => Category::Hazardous,
+            }
+        }
+
+        /// Hex rather than a theme name: people know the categories
+        /// by these colors, so they stay the same in every palette.
+        pub fn color(&self) -> &'static str {
+            match self {
+                Category::Good => "#00e400",
+                Category::Moderate => "#ffff00",
+                Category::UnhealthyForSensitiveGroups => "#ff7e00",
+                Category::Unhealthy => "#ff0000",
+                Category::VeryUnhealthy => "#8f3f97",
+                Category::Hazardous => "#7e0023",
+            }
+        }
+    }
+
+    /// The index for the site's worst pollutant.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Aqi {
+        pub aqi: u32,
+        pub pollutant: Pollutant,
+    }
+
+    impl Aqi {
+        pub fn category(&self) -> Category {
+            Category::from_index(self.aqi)
+        }
+
+        pub fn text(&self) -> TextWidget {
+            TextWidget {
+                text: format!("{} {}", self.aqi, self.pollutant.label()),
+                color: String::from(self.category().color()),
+                font: FontName::default(),
+                stale: false,
+            }
+        }
+    }
+
+    /// The `aqi` section of the config.
+    #[derive(Debug, Clone, Default, Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    pub struct AqiConfig {
+        /// Show the index whatever it is, instead of only
+        /// when it's unhealthy for sensitive groups or worse.
+        pub always_show: bool,
+    }
+
+    #[derive(Debug, Error)]
+    pub enum AqiError {
+        #[error("could not get AirNow data: {0}")]
+        Request(#[from] reqwest::Error),
+        #[error("the site has no readings for any pollutant")]
+        NoReading,
+        #[error(transparent)]
+        Location(#[from] LocationError),
+    }
+
+    /// Air quality from AirNow's nearest site to the location,
+    /// which is only shown when it's bad unless `always_show`.
+    pub struct AqiSource {
+        pub resolver: Arc<Resolver>,
+        pub always_show: bool,
+    }
+
+    #[async_trait]
+    impl DataSource for AqiSource {
+        type Output = Aqi;
+        type Error = AqiError;
+
+        const NAME: &'static str = "aqi";
+
+        fn refresh_interval(&self) -> Duration {
+            Duration::minutes(2)
+        }
+
+        async fn fetch(&self) -> Result<Reading<Aqi>, AqiError> {
+            let site = self.resolver.airnow_site().await?;
+            let resp: Site = reqwest::Client::new()
+                .get(format!(
+                    "https://an_gov_data.s3.amazonaws.com/Sites/{}.json",
+                    site
+                ))
+                .header(USER_AGENT, "tidbyt")
+                .send()
+                .await?
+                .json::<Site>()
+                .await?;
+
+            let aqi = resp
+                .monitors
+                .iter()
+                .filter_map(Monitor::index)
+                .max_by_key(|(_, aqi)| *aqi)
+                .map(|(pollutant, aqi)| Aqi { aqi, pollutant })
+                .ok_or(AqiError::NoReading)?;
+
+            if self.always_show || aqi.category() >= Category::UnhealthyForSensitiveGroups {
+                Ok(Reading::Shown(aqi))
+            } else {
+                Ok(Reading::Hidden)
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// The index for a concentration held steady.
+        fn steady(pollutant: Pollutant, concentration: f64) -> Option<u32> {
+            pollutant.index(&[Some(concentration); 12])
+        }
+
+        fn category(pollutant: Pollutant, concentration: f64) -> Category {
+            Category::from_index(steady(pollutant, concentration).unwrap())
+        }
+
+        #[test]
+        fn categories_split_at_the_epa_boundaries() {
+            let bounds = [
+                (50, Category::Good),
+                (51, Category::Moderate),
+                (100, Category::Moderate),
+                (101, Category::UnhealthyForSensitiveGroups),
+                (150, Category::UnhealthyForSensitiveGroups),
+                (151, Category::Unhealthy),
+                (200, Category::Unhealthy),
+                (201, Category::VeryUnhealthy),
+                (300, Category::VeryUnhealthy),
+                (301, Category::Hazardous),
+                (500, Category::Hazardous),
+            ];
+            for (aqi, category) in bounds {
+                assert_eq!(Category::from_index(aqi), category, "{}", aqi);
+            }
+        }
+
+        #[test]
+        fn pm25_boundaries() {
+            let bounds = [
+                (0.0, 0),
+                (9.0, 50),
+                (9.1, 51),
+                (35.4, 100),
+                (35.5, 101),
+                (55.4, 150),
+                (55.5, 151),
+                (125.4, 200),
+                (125.5, 201),
+                (225.4, 300),
+                (225.5, 301),
+                (325.4, 500),
+                (600.0, 500),
+            ];
+            for (concentration, aqi) in bounds {
+                assert_eq!(
+                    steady(Pollutant::Pm25, concentration),
+                    Some(aqi),
+                    "{}",
+                    concentration
+                );
+            }
+            // Truncated to 9.0, not rounded up into the next range.
+            assert_eq!(category(Pollutant::Pm25, 9.09), Category::Good);
+            assert_eq!(
+                category(Pollutant::Pm25, 35.5),
+                Category::UnhealthyForSensitiveGroups
+            );
+        }
+
+        #[test]
+        fn pm25_now_cast() {
+            // Following the EPA's steps, with the latest hour first:
+            // 30, 20, then ten hours of 10 µg/m³. The lowest over the
+            // highest is 1/3, under the floor of 1/2, so each hour
+            // counts half as much as the one after it:
+            //
+            //   (30 + 20/2 + 10 (1/4 + ... + 1/2048)) / (1 + ... + 1/2048)
+            //   = 44.9951171875 / 1.99951171875 = 22.503...
+            //
+            // which is truncated to 22.5, and sits in 9.1 to 35.4 for
+            // 51 to 100: 51 + (22.5 - 9.1) / (35.4 - 9.1) × 49 = 75.97.
+            let mut hourly = vec![Some(10.0); 10];
+            hourly.extend([Some(20.0), Some(30.0)]);
+            assert!((now_cast(&hourly).unwrap() - 22.503052503052505).abs() < 1e-9);
+            assert_eq!(Pollutant::Pm25.index(&hourly), Some(76));
+
+            // Steadier air weighs the hours more evenly: 10 and 12
+            // give a weight of 5/6, and a NowCast of 10.53.
+            let mut hourly = vec![Some(10.0); 10];
+            hourly.extend([Some(11.0), Some(12.0)]);
+            assert!((now_cast(&hourly).unwrap() - 10.531875611578378).abs() < 1e-9);
+            assert_eq!(Pollutant::Pm25.index(&hourly), Some(54));
+        }
+
+        #[test]
+        fn now_cast_needs_two_of_the_last_three_hours() {
+            assert_eq!(now_cast(&[Some(10.0), None, None]), None);
+            assert_eq!(now_cast(&[Some(10.0), None, Some(10.0)]), Some(10.0));
+            // Older gaps are left out.
+            let hourly = [None, None, None, Some(20.0), Some(20.0)];
+            assert_eq!(now_cast(&hourly), Some(20.0));
+        }
+
+        #[test]
+        fn ozone_uses_eight_hours_below_0_125() {
+            assert_eq!(steady(Pollutant::Ozone, 0.054), Some(50));
+            assert_eq!(steady(Pollutant::Ozone, 0.055), Some(51));
+            assert_eq!(steady(Pollutant::Ozone, 0.070), Some(100));
+            assert_eq!(steady(Pollutant::Ozone, 0.071), Some(101));
+            // An hour this high on its own doesn't count.
+            let mut hourly = vec![Some(0.040); 7];
+            hourly.push(Some(0.120));
+            assert_eq!(Pollutant::Ozone.index(&hourly), Some(46));
+        }
+
+        #[test]
+        fn ozone_takes_the_higher_of_eight_hours_and_one() {
+            // Eight hours average 0.0625, for 74, but the last hour
+            // alone is 0.150, which is 101 + 25/39 × 49 = 132.
+            let mut hourly = vec![Some(0.050); 7];
+            hourly.push(Some(0.150));
+            assert_eq!(Pollutant::Ozone.index(&hourly), Some(132));
+            // And eight bad hours outweigh a slightly worse one.
+            let mut hourly = vec![Some(0.090); 7];
+            hourly.push(Some(0.126));
+            assert_eq!(Pollutant::Ozone.index(&hourly), Some(172));
+        }
+
+        #[test]
+        fn ozone_above_0_200_only_uses_the_hour() {
+            assert_eq!(steady(Pollutant::Ozone, 0.250), Some(223));
+            // Without the last hour there's nothing to go on.
+            let mut hourly = vec![Some(0.250); 7];
+            hourly.push(None);
+            assert_eq!(Pollutant::Ozone.index(&hourly), None);
+        }
+
+        #[test]
+        fn ozone_needs_six_of_eight_hours() {
+            let mut hourly = vec![None; 3];
+            hourly.extend([Some(0.060); 5]);
+            assert_eq!(Pollutant::Ozone.index(&hourly), None);
+            hourly[2] = Some(0.060);
+            assert_eq!(Pollutant::Ozone.index(&hourly), Some(67));
+        }
+
+        #[test]
+        fn co_boundaries() {
+            let bounds = [
+                (4.4, 50),
+                (4.5, 51),
+                (9.4, 100),
+                (9.5, 101),
+                (12.4, 150),
+                (12.5, 151),
+                (15.4, 200),
+                (15.5, 201),
+                (30.4, 300),
+                (30.5, 301),
+            ];
+            for (concentration, aqi) in bounds {
+                assert_eq!(
+                    steady(Pollutant::Co, concentration),
+                    Some(aqi),
+                    "{}",
+                    concentration
+                );
+            }
+        }
+
+        #[test]
+        fn co_falls_back_to_the_latest_hour() {
+            let hourly = [None, None, None, Some(2.0), Some(9.5)];
+            assert_eq!(Pollutant::Co.index(&hourly), Some(101));
+        }
+
+        #[test]
+        fn so2_boundaries() {
+            let bounds = [
+                (35.0, 50),
+                (35.9, 50),
+                (36.0, 51),
+                (75.0, 100),
+                (76.0, 101),
+                (185.0, 150),
+                (186.0, 151),
+                (304.0, 200),
+                (305.0, 201),
+                (604.0, 300),
+                (605.0, 301),
+                (1004.0, 500),
+            ];
+            for (concentration, aqi) in bounds {
+                assert_eq!(
+                    steady(Pollutant::So2, concentration),
+                    Some(aqi),
+                    "{}",
+                    concentration
+                );
+            }
+            // Only the latest hour counts.
+            assert_eq!(Pollutant::So2.index(&[Some(500.0), Some(20.0)]), Some(29));
+        }
+
+        #[test]
+        fn monitors_convert_units() {
+            let monitor: Monitor = serde_json::from_str(
+                r#"{"parameterName": "OZONE", "concUnit": "PPB", "conc": [55, 55, 55, 55, 55, 55, 55, 55]}"#,
+            )
+            .unwrap();
+            assert_eq!(monitor.index(), Some((Pollutant::Ozone, 51)));
+        }
+
+        #[test]
+        fn monitors_fall_back_to_airnows_index() {
+            let monitor: Monitor = serde_json::from_str(
+                r#"{"parameterName": "PM2.5", "concUnit": "MG/M3", "conc": [1, 1, 1], "aqi": [40, 42.4, -999]}"#,
+            )
+            .unwrap();
+            assert_eq!(monitor.index(), Some((Pollutant::Pm25, 42)));
+            let monitor: Monitor =
+                serde_json::from_str(r#"{"parameterName": "PM2.5", "conc": [-999, -999]}"#)
+                    .unwrap();
+            assert_eq!(monitor.index(), None);
         }
     }
 }
@@ -0,0 +1,445 @@
+/// How bright the display should be, following the sun, with
+/// quiet hours that blank it and a manual override.
+pub mod brightness {
+    use anyhow::{anyhow, Result};
+    use chrono::{DateTime, FixedOffset, NaiveTime, TimeZone};
+    use serde::Deserialize;
+    use suncalc::Timestamp;
+
+    /// How many levels there are between off and full brightness.
+    /// Levels are rounded to these, so that the display only changes,
+    /// and is only pushed again, when the brightness visibly does.
+    const STEPS: f64 = 50.0;
+
+    /// Round a level to the nearest step.
+    pub fn quantize(level: f64) -> f64 {
+        (level.clamp(0.0, 1.0) * STEPS).round() / STEPS
+    }
+
+    /// How far below the horizon the sun goes before the display is
+    /// at its dimmest. It brightens from there as the sun comes up.
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Twilight {
+        /// The sun's top edge at the horizon.
+        Sunset,
+        /// 6° below, when it's too dark to read outside.
+        #[default]
+        Civil,
+        /// 12° below, when the horizon can't be seen at sea.
+        Nautical,
+    }
+
+    impl Twilight {
+        /// The sun's altitude, in degrees.
+        fn altitude(&self) -> f64 {
+            match self {
+                Twilight::Sunset => -0.833,
+                Twilight::Civil => -6.0,
+                Twilight::Nautical => -12.0,
+            }
+        }
+    }
+
+    /// How brightness goes from `min` to `max` as the sun rises.
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Curve {
+        /// In proportion to the sun's altitude.
+        Linear,
+        /// Easing in and out at either end.
+        #[default]
+        Smooth,
+        /// Straight from `min` to `max` at the twilight altitude.
+        Step,
+    }
+
+    impl Curve {
+        /// Map progress from 0 to 1 on to the curve.
+        fn apply(&self, t: f64) -> f64 {
+            match self {
+                Curve::Linear => t,
+                Curve::Smooth => t * t * (3.0 - 2.0 * t),
+                Curve::Step => 1.0,
+            }
+        }
+    }
+
+    /// A wall-clock time like `23:30`.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct TimeOfDay(pub NaiveTime);
+
+    impl<'de> Deserialize<'de> for TimeOfDay {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let time = String::deserialize(deserializer)?;
+            NaiveTime::parse_from_str(&time, "%H:%M")
+                .map(TimeOfDay)
+                .map_err(|_| serde::de::Error::custom(format!("invalid time `{}`", time)))
+        }
+    }
+
+    /// When the display is blank. `end` can be earlier than
+    /// `start`, for hours that go past midnight.
+    #[derive(Copy, Clone, Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct QuietHours {
+        pub start: TimeOfDay,
+        pub end: TimeOfDay,
+    }
+
+    impl QuietHours {
+        fn contains(&self, time: NaiveTime) -> bool {
+            let (start, end) = (self.start.0, self.end.0);
+            if start <= end {
+                start <= time && time < end
+            } else {
+                time >= start || time < end
+            }
+        }
+    }
+
+    /// A fixed level, either for good or `until` a time,
+    /// given as a number or as `{"level": 0.5, "until": "..."}`.
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub struct Override {
+        pub level: f64,
+        pub until: Option<DateTime<FixedOffset>>,
+    }
+
+    impl<'de> Deserialize<'de> for Override {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            #[derive(Deserialize)]
+            #[serde(untagged)]
+            enum Given {
+                Level(f64),
+                Until {
+                    level: f64,
+                    until: Option<DateTime<FixedOffset>>,
+                },
+            }
+            Ok(
+                match Given::deserialize(deserializer).map_err(|_| {
+                    serde::de::Error::custom(
+                        "expected a level, or a `level` with an RFC 3339 time `until`",
+                    )
+                })? {
+                    Given::Level(level) => Override { level, until: None },
+                    Given::Until { level, until } => Override { level, until },
+                },
+            )
+        }
+    }
+
+    impl Override {
+        fn active<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> bool {
+            self.until.is_none_or(|until| *at < until)
+        }
+    }
+
+    /// The `brightness` section of the config, for all
+    /// devices or one. Levels go from 0 for off to 1.
+    #[derive(Clone, Debug, Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    pub struct BrightnessPolicy {
+        pub twilight: Twilight,
+        /// How bright it is at night.
+        pub min: f64,
+        /// How bright it gets in the day.
+        pub max: f64,
+        /// The sun's altitude in degrees by which it's at `max`.
+        pub full_at: f64,
+        pub curve: Curve,
+        pub quiet_hours: Option<QuietHours>,
+        /// A fixed level, ignoring the sun and quiet hours
+        /// until it expires.
+        #[serde(rename = "override")]
+        pub fixed: Option<Override>,
+    }
+
+    impl Default for BrightnessPolicy {
+        fn default() -> BrightnessPolicy {
+            BrightnessPolicy {
+                twilight: Twilight::default(),
+                min: 0.2,
+                max: 1.0,
+                full_at: 30.0,
+                curve: Curve::default(),
+                quiet_hours: None,
+                fixed: None,
+            }
+        }
+    }
+
+    impl BrightnessPolicy {
+        /// Check everything that serde can't.
+        pub fn validate(&self) -> Result<()> {
+            let level = |name: &str, value: f64| {
+                if (0.0..=1.0).contains(&value) {
+                    Ok(())
+                } else {
+                    Err(anyhow!("{} must be between 0 and 1, not {}", name, value))
+                }
+            };
+            level("min", self.min)?;
+            level("max", self.max)?;
+            if let Some(fixed) = self.fixed {
+                level("override", fixed.level)?;
+            }
+            if self.min > self.max {
+                return Err(anyhow!("min {} is above max {}", self.min, self.max));
+            }
+            if self.full_at <= self.twilight.altitude() || self.full_at > 90.0 {
+                return Err(anyhow!(
+                    "full_at must be between the twilight altitude and 90, not {}",
+                    self.full_at
+                ));
+            }
+            Ok(())
+        }
+
+        /// The level at a time and place. Quiet hours go by the
+        /// wall clock in `at`'s time zone.
+        pub fn level<Tz: TimeZone>(&self, at: &DateTime<Tz>, lat: f64, lon: f64) -> f64 {
+            if let Some(fixed) = self.fixed.filter(|fixed| fixed.active(at)) {
+                return fixed.level;
+            }
+            let time = at.naive_local().time();
+            if self.quiet_hours.is_some_and(|quiet| quiet.contains(time)) {
+                return 0.0;
+            }
+            let position = suncalc::get_position(Timestamp(at.timestamp_millis()), lat, lon);
+            self.at_altitude(position.altitude.to_degrees())
+        }
+
+        /// The level with the sun `altitude` degrees up.
+        fn at_altitude(&self, altitude: f64) -> f64 {
+            let low = self.twilight.altitude();
+            if altitude < low {
+                return self.min;
+            }
+            let t = ((altitude - low) / (self.full_at - low)).clamp(0.0, 1.0);
+            self.min + (self.max - self.min) * self.curve.apply(t)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::Utc;
+
+        // Brooklyn, where the sun is 72.7° up at its highest on
+        // the solstice, 2026-06-21, at about 17:00 UTC.
+        const LAT: f64 = 40.692778;
+        const LON: f64 = -73.990278;
+
+        fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+            Utc.with_ymd_and_hms(2026, 6, 21, hour, minute, 0).unwrap()
+        }
+
+        fn level(policy: &BrightnessPolicy, at: DateTime<Utc>) -> f64 {
+            policy.level(&at, LAT, LON)
+        }
+
+        fn close(a: f64, b: f64) -> bool {
+            (a - b).abs() < 1e-9
+        }
+
+        fn time(text: &str) -> TimeOfDay {
+            TimeOfDay(NaiveTime::parse_from_str(text, "%H:%M").unwrap())
+        }
+
+        fn quiet(start: &str, end: &str) -> BrightnessPolicy {
+            BrightnessPolicy {
+                quiet_hours: Some(QuietHours {
+                    start: time(start),
+                    end: time(end),
+                }),
+                ..BrightnessPolicy::default()
+            }
+        }
+
+        #[test]
+        fn deep_night_is_at_min() {
+            let policy = BrightnessPolicy::default();
+            // Midnight in New York, with the sun 25° down.
+            assert_eq!(level(&policy, at(4, 0)), 0.2);
+        }
+
+        #[test]
+        fn midday_is_at_max() {
+            let policy = BrightnessPolicy::default();
+            assert_eq!(level(&policy, at(17, 0)), 1.0);
+        }
+
+        #[test]
+        fn brightens_through_twilight() {
+            let policy = BrightnessPolicy::default();
+            // The sun is 9.2° down at 8:30, so still below civil
+            // twilight, then 4.8° down, 4.9° up, 15.4° up, 26.5°
+            // up and 32.1° up, past `full_at`.
+            assert_eq!(level(&policy, at(8, 30)), policy.min);
+            let levels: Vec<f64> = [(9, 0), (10, 0), (11, 0), (12, 0)]
+                .iter()
+                .map(|(hour, minute)| level(&policy, at(*hour, *minute)))
+                .collect();
+            assert!(levels[0] > policy.min);
+            assert!(levels.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(levels[3] < policy.max);
+            assert_eq!(level(&policy, at(12, 30)), policy.max);
+        }
+
+        #[test]
+        fn nautical_twilight_starts_brightening_sooner() {
+            let policy = BrightnessPolicy {
+                twilight: Twilight::Nautical,
+                ..BrightnessPolicy::default()
+            };
+            assert!(level(&policy, at(8, 30)) > policy.min);
+            assert_eq!(level(&policy, at(8, 0)), policy.min);
+        }
+
+        #[test]
+        fn reaches_max_at_full_at() {
+            let policy = BrightnessPolicy {
+                full_at: 20.0,
+                ..BrightnessPolicy::default()
+            };
+            assert!(close(policy.at_altitude(20.0), policy.max));
+            assert!(policy.at_altitude(19.9) < policy.max);
+            assert!(close(policy.at_altitude(45.0), policy.max));
+            // 15.4° up, then 20.9° up.
+            assert!(level(&policy, at(11, 0)) < policy.max);
+            assert_eq!(level(&policy, at(11, 30)), policy.max);
+        }
+
+        #[test]
+        fn curves_shape_the_ramp() {
+            let with = |curve: Curve| BrightnessPolicy {
+                curve,
+                ..BrightnessPolicy::default()
+            };
+            // From -6° to 30°, 3° is a quarter of the way
+            // and 12° is halfway.
+            let linear = with(Curve::Linear);
+            assert!(close(linear.at_altitude(3.0), 0.4));
+            assert!(close(linear.at_altitude(12.0), 0.6));
+            let smooth = with(Curve::Smooth);
+            assert!(close(smooth.at_altitude(3.0), 0.2 + 0.8 * 0.15625));
+            assert!(close(smooth.at_altitude(12.0), 0.6));
+            assert!(close(smooth.at_altitude(-6.0), 0.2));
+            let step = with(Curve::Step);
+            assert_eq!(step.at_altitude(-6.1), 0.2);
+            assert_eq!(step.at_altitude(-6.0), 1.0);
+            assert_eq!(step.at_altitude(3.0), 1.0);
+        }
+
+        #[test]
+        fn quiet_hours_can_wrap_past_midnight() {
+            let policy = quiet("22:00", "07:00");
+            assert!(level(&policy, at(21, 59)) > policy.min);
+            assert_eq!(level(&policy, at(22, 0)), 0.0);
+            assert_eq!(level(&policy, at(23, 30)), 0.0);
+            assert_eq!(level(&policy, at(0, 0)), 0.0);
+            assert_eq!(level(&policy, at(6, 59)), 0.0);
+            assert_eq!(level(&policy, at(7, 0)), policy.min);
+        }
+
+        #[test]
+        fn quiet_hours_within_a_day() {
+            let policy = quiet("12:00", "13:00");
+            assert!(level(&policy, at(11, 59)) > 0.0);
+            assert_eq!(level(&policy, at(12, 0)), 0.0);
+            assert_eq!(level(&policy, at(13, 0)), 1.0);
+        }
+
+        #[test]
+        fn quiet_hours_go_by_the_local_clock() {
+            let policy = quiet("23:00", "01:00");
+            let new_york = FixedOffset::west_opt(4 * 3600).unwrap();
+            // 03:30 UTC is 23:30 in New York.
+            assert_eq!(
+                policy.level(&at(3, 30).with_timezone(&new_york), LAT, LON),
+                0.0
+            );
+            assert_eq!(level(&policy, at(3, 30)), policy.min);
+        }
+
+        #[test]
+        fn overrides_win_until_they_expire() {
+            let until = at(5, 0).fixed_offset();
+            let policy = BrightnessPolicy {
+                fixed: Some(Override {
+                    level: 0.7,
+                    until: Some(until),
+                }),
+                ..quiet("22:00", "07:00")
+            };
+            assert_eq!(level(&policy, at(4, 59)), 0.7);
+            assert_eq!(level(&policy, at(5, 0)), 0.0);
+            let policy = BrightnessPolicy {
+                fixed: Some(Override {
+                    level: 0.7,
+                    until: Some(until),
+                }),
+                ..BrightnessPolicy::default()
+            };
+            assert_eq!(level(&policy, at(5, 0)), policy.min);
+        }
+
+        #[test]
+        fn overrides_without_an_end_last() {
+            let policy = BrightnessPolicy {
+                fixed: Some(Override {
+                    level: 0.3,
+                    until: None,
+                }),
+                ..BrightnessPolicy::default()
+            };
+            assert_eq!(level(&policy, at(4, 0)), 0.3);
+            assert_eq!(level(&policy, at(17, 0)), 0.3);
+        }
+
+        #[test]
+        fn overrides_are_a_level_or_a_level_until() {
+            let parse = |json: &str| serde_json::from_str::<BrightnessPolicy>(json);
+            let policy = parse(r#"{"override": 0.5}"#).unwrap();
+            assert_eq!(
+                policy.fixed,
+                Some(Override {
+                    level: 0.5,
+                    until: None
+                })
+            );
+            let policy =
+                parse(r#"{"override": {"level": 0.5, "until": "2026-06-21T01:00:00-04:00"}}"#)
+                    .unwrap();
+            assert_eq!(policy.fixed.unwrap().until, Some(at(5, 0).fixed_offset()));
+            assert!(parse(r#"{"override": "bright"}"#).is_err());
+            assert!(parse(r#"{"override": {"level": 0.5, "until": "tonight"}}"#).is_err());
+        }
+
+        #[test]
+        fn validates_levels() {
+            assert!(BrightnessPolicy::default().validate().is_ok());
+            let policy = BrightnessPolicy {
+                fixed: Some(Override {
+                    level: 1.5,
+                    until: None,
+                }),
+                ..BrightnessPolicy::default()
+            };
+            assert!(policy.validate().is_err());
+            let policy = BrightnessPolicy {
+                min: 0.8,
+                max: 0.5,
+                ..BrightnessPolicy::default()
+            };
+            assert!(policy.validate().is_err());
+            let policy = BrightnessPolicy {
+                full_at: -10.0,
+                ..BrightnessPolicy::default()
+            };
+            assert!(policy.validate().is_err());
+        }
+    }
+}
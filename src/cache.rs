@@ -0,0 +1,65 @@
+/// Source readings saved to disk, so that a restart can show
+/// the last known values straight away.
+pub mod cache {
+    use crate::source::source::SourceState;
+    use anyhow::{Context, Result};
+    use chrono::{Duration, Utc};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// Where state lives when `--state-dir` isn't given.
+    pub fn default_state_dir() -> Result<PathBuf> {
+        dirs::cache_dir()
+            .or_else(dirs::home_dir)
+            .map(|path| path.join("tidbyt-rs"))
+            .context("Could not find a directory for the cache")
+    }
+
+    /// A JSON file for each source in the state directory.
+    pub struct DiskCache {
+        dir: PathBuf,
+    }
+
+    impl DiskCache {
+        pub fn new(dir: &Path) -> Result<DiskCache> {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create {}", dir.display()))?;
+            Ok(DiskCache {
+                dir: dir.to_path_buf(),
+            })
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.join(format!("{}.json", name))
+        }
+
+        /// A source's saved state, unless there isn't one or it was
+        /// fetched longer than `ttl` ago and isn't worth showing.
+        pub fn load(&self, name: &str, ttl: Duration) -> Option<SourceState> {
+            let json = fs::read_to_string(self.path(name)).ok()?;
+            let state: SourceState = match serde_json::from_str(&json) {
+                Ok(state) => state,
+                Err(err) => {
+                    println!("Ignoring cached {}: {}", name, err);
+                    return None;
+                }
+            };
+            match state.fetched_at {
+                Some(fetched_at) if Utc::now() - fetched_at > ttl => None,
+                _ => Some(state),
+            }
+        }
+
+        /// Save a source's state, writing to a temporary file first
+        /// so that a crash can't leave half a file behind.
+        pub fn store(&self, name: &str, state: &SourceState) -> Result<()> {
+            let path = self.path(name);
+            let temporary = path.with_extension("json.tmp");
+            fs::write(&temporary, serde_json::to_vec(state)?)
+                .with_context(|| format!("Could not write {}", temporary.display()))?;
+            fs::rename(&temporary, &path)
+                .with_context(|| format!("Could not write {}", path.display()))?;
+            Ok(())
+        }
+    }
+}
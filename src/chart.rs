@@ -0,0 +1,566 @@
+/// Small charts of one or more series, a pixel per value.
+pub mod chart {
+    use crate::adjusted_color::adjusted_color::{adjusted_color, adjusted_color_with_tint};
+    use crate::theme::theme::resolve;
+    use anyhow::{anyhow, Result};
+    use palette::{Mix, Srgb};
+    use raqote::{DrawOptions, DrawTarget, Point, Source};
+    use serde::Deserialize;
+
+    /// How much darker an area's fill is than its top edge.
+    const AREA_TINT: f64 = 0.5;
+
+    /// How a series is drawn.
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ChartKind {
+        /// A bar from zero up or down to each value.
+        #[default]
+        Bar,
+        /// A line through the values.
+        Line,
+        /// A bar with a bright top, filled in more dimly.
+        Area,
+        /// A pixel at each value.
+        Dot,
+    }
+
+    /// The values at the bottom and top of a chart. Either end
+    /// that isn't given is scaled to fit the values, and bars
+    /// and areas always include zero.
+    #[derive(Copy, Clone, Debug, Default, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Range {
+        pub min: Option<f64>,
+        pub max: Option<f64>,
+    }
+
+    /// A value from which a ramp uses `color`.
+    #[derive(Clone, Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Stop {
+        pub at: f64,
+        pub color: String,
+    }
+
+    /// Colors by value. Each value takes the color of the last stop at or
+    /// below it, or with `smooth`, a blend of the stops on either side.
+    /// Values below the first stop take its color.
+    #[derive(Clone, Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Ramp {
+        pub stops: Vec<Stop>,
+        #[serde(default)]
+        pub smooth: bool,
+    }
+
+    fn parse_color(color: &str) -> Result<Srgb<f32>> {
+        Ok(resolve(color)?.into_format())
+    }
+
+    impl Ramp {
+        /// Check that there are stops, in order, with valid colors.
+        pub fn validate(&self) -> Result<()> {
+            if self.stops.is_empty() {
+                return Err(anyhow!("ramp has no stops"));
+            }
+            if self.stops.windows(2).any(|pair| pair[0].at >= pair[1].at) {
+                return Err(anyhow!("ramp stops must go up in value"));
+            }
+            for stop in &self.stops {
+                parse_color(&stop.color)?;
+            }
+            Ok(())
+        }
+
+        fn color(&self, value: f64) -> Result<String> {
+            let above = self.stops.iter().position(|stop| stop.at > value);
+            let (below, above) = match above {
+                Some(0) => return Ok(self.stops[0].color.clone()),
+                Some(i) => (&self.stops[i - 1], &self.stops[i]),
+                None => return Ok(self.stops[self.stops.len() - 1].color.clone()),
+            };
+            if !self.smooth {
+                return Ok(below.color.clone());
+            }
+            let factor = ((value - below.at) / (above.at - below.at)) as f32;
+            let mixed = parse_color(&below.color)?
+                .into_linear()
+                .mix(parse_color(&above.color)?.into_linear(), factor);
+            let Srgb {
+                red, green, blue, ..
+            } = Srgb::<u8>::from_linear(mixed);
+            Ok(format!("#{:02x}{:02x}{:02x}", red, green, blue))
+        }
+    }
+
+    /// One set of values on a chart, each in its own
+    /// pixel column. `None` leaves a gap.
+    #[derive(Clone, Debug)]
+    pub struct Series {
+        values: Vec<Option<f64>>,
+        kind: ChartKind,
+        range: Range,
+        color: String,
+        ramp: Option<Ramp>,
+    }
+
+    impl Series {
+        pub fn new(values: Vec<f64>) -> Series {
+            Series::sparse(values.into_iter().map(Some).collect())
+        }
+
+        pub fn sparse(values: Vec<Option<f64>>) -> Series {
+            Series {
+                values,
+                kind: ChartKind::default(),
+                range: Range::default(),
+                color: String::from("text"),
+                ramp: None,
+            }
+        }
+
+        pub fn set_kind(mut self, kind: ChartKind) -> Series {
+            self.kind = kind;
+            self
+        }
+
+        pub fn set_range(mut self, range: Range) -> Series {
+            self.range = range;
+            self
+        }
+
+        /// Use one color throughout, instead of any ramp.
+        pub fn set_color(mut self, color: String) -> Series {
+            self.color = color;
+            self.ramp = None;
+            self
+        }
+
+        pub fn set_ramp(mut self, ramp: Ramp) -> Series {
+            self.ramp = Some(ramp);
+            self
+        }
+
+        /// The bottom and top of the series' range, filling in either
+        /// end that isn't fixed from the values and `also`, if given.
+        fn bounds(&self, also: Option<f64>) -> (f64, f64) {
+            let values = self.values.iter().flatten().chain(also.as_ref()).copied();
+            let (mut low, mut high) = values.fold((f64::MAX, f64::MIN), |(low, high), value| {
+                (low.min(value), high.max(value))
+            });
+            if low > high {
+                (low, high) = (0.0, 0.0);
+            }
+            if matches!(self.kind, ChartKind::Bar | ChartKind::Area) {
+                (low, high) = (low.min(0.0), high.max(0.0));
+            }
+            let min = self.range.min.unwrap_or(low);
+            let max = self.range.max.unwrap_or(high);
+            if max > min {
+                (min, max)
+            } else {
+                (min, min + 1.0)
+            }
+        }
+
+        fn color(&self, value: f64) -> Result<String> {
+            match &self.ramp {
+                Some(ramp) => ramp.color(value),
+                None => Ok(self.color.clone()),
+            }
+        }
+    }
+
+    /// A horizontal line across the chart at a value
+    /// on the first series' scale, drawn dotted.
+    #[derive(Clone, Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Goal {
+        pub value: f64,
+        #[serde(default = "default_goal_color")]
+        pub color: String,
+    }
+
+    fn default_goal_color() -> String {
+        String::from("muted")
+    }
+
+    /// Series drawn over each other, first to last, in a box
+    /// `height` pixels tall and a pixel wide per value.
+    #[derive(Clone, Debug)]
+    pub struct Chart {
+        series: Vec<Series>,
+        height: u32,
+        goal: Option<Goal>,
+    }
+
+    impl Chart {
+        pub fn new(series: Series) -> Chart {
+            Chart {
+                series: vec![series],
+                height: 8,
+                goal: None,
+            }
+        }
+
+        pub fn add_series(mut self, series: Series) -> Chart {
+            self.series.push(series);
+            self
+        }
+
+        pub fn set_height(mut self, height: u32) -> Chart {
+            self.height = height.max(1);
+            self
+        }
+
+        pub fn set_goal(mut self, goal: Goal) -> Chart {
+            self.goal = Some(goal);
+            self
+        }
+
+        /// Change the first series, which is the one that
+        /// layouts style and goals are measured against.
+        pub fn map_primary(mut self, f: impl FnOnce(Series) -> Series) -> Chart {
+            let primary = self.series.remove(0);
+            self.series.insert(0, f(primary));
+            self
+        }
+
+        pub fn width(&self) -> u32 {
+            self.series
+                .iter()
+                .map(|series| series.values.len() as u32)
+                .max()
+                .unwrap_or(0)
+        }
+
+        pub fn height(&self) -> u32 {
+            self.height
+        }
+
+        /// Check that there's something to draw, and that it's all numbers.
+        pub fn validate(&self) -> Result<()> {
+            if self.series.iter().all(|series| series.values.is_empty()) {
+                return Err(anyhow!("chart has no values"));
+            }
+            let values = self.series.iter().flat_map(|series| series.values.iter());
+            if let Some(value) = values.flatten().find(|value| !value.is_finite()) {
+                return Err(anyhow!("chart has a value that isn't a number: {}", value));
+            }
+            if let Some(goal) = self.goal.as_ref().filter(|goal| !goal.value.is_finite()) {
+                return Err(anyhow!("goal isn't a number: {}", goal.value));
+            }
+            Ok(())
+        }
+
+        /// The bounds of series `i`. The first series' take in the
+        /// goal, so the goal line is on the chart.
+        fn bounds(&self, i: usize) -> (f64, f64) {
+            let goal = match i {
+                0 => self.goal.as_ref().map(|goal| goal.value),
+                _ => None,
+            };
+            self.series[i].bounds(goal)
+        }
+
+        /// The pixel row for a value, counting up from the bottom.
+        fn row(&self, value: f64, (min, max): (f64, f64)) -> i32 {
+            let top = (self.height - 1) as f64;
+            ((value.clamp(min, max) - min) / (max - min) * top).round() as i32
+        }
+
+        /// Fill rows `from` to `to` of column `x`, in either order.
+        fn fill(
+            &self,
+            dt: &mut DrawTarget,
+            point: Point,
+            x: usize,
+            from: i32,
+            to: i32,
+            color: &Source,
+        ) {
+            let (low, high) = (from.min(to), from.max(to));
+            let bottom = point.y + self.height as f32;
+            dt.fill_rect(
+                point.x + x as f32,
+                bottom - (high + 1) as f32,
+                1.0,
+                (high - low + 1) as f32,
+                color,
+                &DrawOptions::new(),
+            );
+        }
+
+        /// Draw the chart with its top left at `point`.
+        pub fn render(&self, dt: &mut DrawTarget, point: Point) -> Result<()> {
+            if let (Some(goal), false) = (&self.goal, self.series.is_empty()) {
+                let row = self.row(goal.value, self.bounds(0));
+                let color = adjusted_color(&goal.color)?;
+                for x in (0..self.width() as usize).step_by(2) {
+                    self.fill(dt, point, x, row, row, &color);
+                }
+            }
+            for (i, series) in self.series.iter().enumerate() {
+                let bounds = self.bounds(i);
+                let base = self.row(0.0, bounds);
+                let mut previous: Option<i32> = None;
+                for (x, value) in series.values.iter().enumerate() {
+                    let Some(value) = value else {
+                        previous = None;
+                        continue;
+                    };
+                    let row = self.row(*value, bounds);
+                    let hex = series.color(*value)?;
+                    let color = adjusted_color(&hex)?;
+                    match series.kind {
+                        ChartKind::Bar => self.fill(dt, point, x, base, row, &color),
+                        ChartKind::Area => {
+                            let fill = adjusted_color_with_tint(&hex, AREA_TINT)?;
+                            self.fill(dt, point, x, base, row, &fill);
+                            self.fill(dt, point, x, row, row, &color);
+                        }
+                        ChartKind::Line => {
+                            // Join on to the previous value, so steps
+                            // don't leave gaps.
+                            let from = match previous {
+                                Some(previous) if previous < row => previous + 1,
+                                Some(previous) if previous > row => previous - 1,
+                                _ => row,
+                            };
+                            self.fill(dt, point, x, from, row, &color);
+                        }
+                        ChartKind::Dot => self.fill(dt, point, x, row, row, &color),
+                    }
+                    previous = Some(row);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Overrides for a chart from a layout. `kind`, `range`, `color`
+    /// and `ramp` apply to the first series.
+    #[derive(Clone, Debug, Default, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct ChartStyle {
+        pub kind: Option<ChartKind>,
+        pub range: Option<Range>,
+        pub color: Option<String>,
+        pub ramp: Option<Ramp>,
+        pub height: Option<u32>,
+        pub goal: Option<Goal>,
+    }
+
+    impl ChartStyle {
+        /// Check everything that serde can't.
+        pub fn validate(&self) -> Result<()> {
+            if let Some(Range {
+                min: Some(min),
+                max: Some(max),
+            }) = self.range
+            {
+                if min >= max {
+                    return Err(anyhow!("range min {} must be below max {}", min, max));
+                }
+            }
+            if self.color.is_some() && self.ramp.is_some() {
+                return Err(anyhow!("has both `color` and `ramp`"));
+            }
+            if let Some(color) = &self.color {
+                parse_color(color)?;
+            }
+            if let Some(ramp) = &self.ramp {
+                ramp.validate()?;
+            }
+            if let Some(height) = self.height {
+                if !(1..=32).contains(&height) {
+                    return Err(anyhow!("height must be between 1 and 32, not {}", height));
+                }
+            }
+            if let Some(goal) = &self.goal {
+                parse_color(&goal.color)?;
+            }
+            Ok(())
+        }
+
+        pub fn apply(&self, mut chart: Chart) -> Chart {
+            chart = chart.map_primary(|mut series| {
+                if let Some(kind) = self.kind {
+                    series = series.set_kind(kind);
+                }
+                if let Some(range) = self.range {
+                    series = series.set_range(range);
+                }
+                if let Some(color) = &self.color {
+                    series = series.set_color(color.clone());
+                }
+                if let Some(ramp) = &self.ramp {
+                    series = series.set_ramp(ramp.clone());
+                }
+                series
+            });
+            if let Some(height) = self.height {
+                chart = chart.set_height(height);
+            }
+            if let Some(goal) = &self.goal {
+                chart = chart.set_goal(goal.clone());
+            }
+            chart
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn range(min: Option<f64>, max: Option<f64>) -> Range {
+            Range { min, max }
+        }
+
+        fn goal(value: f64) -> Goal {
+            Goal {
+                value,
+                color: default_goal_color(),
+            }
+        }
+
+        fn black_to_white(smooth: bool) -> Ramp {
+            Ramp {
+                stops: vec![
+                    Stop {
+                        at: 0.0,
+                        color: String::from("#000"),
+                    },
+                    Stop {
+                        at: 1.0,
+                        color: String::from("#fff"),
+                    },
+                ],
+                smooth,
+            }
+        }
+
+        #[test]
+        fn all_negative_bars_reach_up_to_zero() {
+            let chart = Chart::new(Series::new(vec![-3.0, -1.0]));
+            assert_eq!(chart.bounds(0), (-3.0, 0.0));
+            let chart = Chart::new(Series::new(vec![-3.0, -1.0]).set_kind(ChartKind::Line));
+            assert_eq!(chart.bounds(0), (-3.0, -1.0));
+        }
+
+        #[test]
+        fn empty_and_flat_series_still_have_a_range() {
+            let series = Series::new(vec![]).set_kind(ChartKind::Line);
+            assert_eq!(series.bounds(None), (0.0, 1.0));
+            let series = Series::new(vec![4.0, 4.0]).set_kind(ChartKind::Line);
+            assert_eq!(series.bounds(None), (4.0, 5.0));
+        }
+
+        #[test]
+        fn autoscaled_charts_take_in_the_goal() {
+            let chart = Chart::new(Series::new(vec![1.0, 2.0])).set_goal(goal(5.0));
+            assert_eq!(chart.bounds(0), (0.0, 5.0));
+            let chart = Chart::new(Series::new(vec![-1.0, -2.0]).set_kind(ChartKind::Line))
+                .set_goal(goal(-5.0));
+            assert_eq!(chart.bounds(0), (-5.0, -1.0));
+        }
+
+        #[test]
+        fn fixed_ends_ignore_the_goal() {
+            let chart = Chart::new(Series::new(vec![1.0, 2.0]).set_range(range(None, Some(3.0))))
+                .set_goal(goal(5.0));
+            assert_eq!(chart.bounds(0), (0.0, 3.0));
+        }
+
+        #[test]
+        fn only_the_first_series_takes_in_the_goal() {
+            let chart = Chart::new(Series::new(vec![1.0]))
+                .add_series(Series::new(vec![1.0]))
+                .set_goal(goal(5.0));
+            assert_eq!(chart.bounds(1), (0.0, 1.0));
+        }
+
+        #[test]
+        fn rows_span_the_height_and_clamp_at_the_edges() {
+            let chart = Chart::new(Series::new(vec![0.0])).set_height(8);
+            let bounds = (-2.0, 12.0);
+            assert_eq!(chart.row(-2.0, bounds), 0);
+            assert_eq!(chart.row(12.0, bounds), 7);
+            assert_eq!(chart.row(5.0, bounds), 4);
+            assert_eq!(chart.row(-100.0, bounds), 0);
+            assert_eq!(chart.row(100.0, bounds), 7);
+        }
+
+        #[test]
+        fn a_one_pixel_chart_has_one_row() {
+            let chart = Chart::new(Series::new(vec![0.0])).set_height(0);
+            assert_eq!(chart.height(), 1);
+            assert_eq!(chart.row(10.0, (0.0, 10.0)), 0);
+        }
+
+        #[test]
+        fn ramps_color_at_and_beyond_their_stops() {
+            let ramp = black_to_white(false);
+            assert_eq!(ramp.color(0.0).unwrap(), "#000");
+            assert_eq!(ramp.color(0.5).unwrap(), "#000");
+            assert_eq!(ramp.color(1.0).unwrap(), "#fff");
+            assert_eq!(ramp.color(-1.0).unwrap(), "#000");
+            assert_eq!(ramp.color(2.0).unwrap(), "#fff");
+        }
+
+        #[test]
+        fn smooth_ramps_blend_in_linear_light() {
+            let ramp = black_to_white(true);
+            assert_eq!(ramp.color(0.0).unwrap(), "#000000");
+            assert_eq!(ramp.color(0.5).unwrap(), "#bcbcbc");
+            assert_eq!(ramp.color(1.0).unwrap(), "#fff");
+            assert_eq!(ramp.color(-1.0).unwrap(), "#000");
+            assert_eq!(ramp.color(2.0).unwrap(), "#fff");
+        }
+
+        #[test]
+        fn ramps_need_stops_in_order() {
+            assert!(black_to_white(false).validate().is_ok());
+            let mut ramp = black_to_white(false);
+            ramp.stops.reverse();
+            assert!(ramp.validate().is_err());
+            ramp.stops.clear();
+            assert!(ramp.validate().is_err());
+        }
+
+        #[test]
+        fn charts_need_values_that_are_numbers() {
+            assert!(Chart::new(Series::new(vec![1.0])).validate().is_ok());
+            assert!(Chart::new(Series::sparse(vec![None])).validate().is_ok());
+            let err = Chart::new(Series::new(vec![])).validate().unwrap_err();
+            assert_eq!(err.to_string(), "chart has no values");
+            let err = Chart::new(Series::new(vec![1.0, f64::NAN]))
+                .validate()
+                .unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "chart has a value that isn't a number: NaN"
+            );
+            let chart =
+                Chart::new(Series::new(vec![1.0])).add_series(Series::new(vec![f64::INFINITY]));
+            assert!(chart.validate().is_err());
+            let chart = Chart::new(Series::new(vec![1.0])).set_goal(goal(f64::NAN));
+            assert!(chart.validate().is_err());
+        }
+
+        #[test]
+        fn the_goal_line_is_drawn_on_an_autoscaled_chart() {
+            let chart = Chart::new(Series::new(vec![1.0, 0.0, 0.0]))
+                .set_height(5)
+                .set_goal(goal(4.0));
+            let mut dt = DrawTarget::new(3, 5);
+            chart.render(&mut dt, Point::new(0., 0.)).unwrap();
+            let lit = |x: usize, y: usize| dt.get_data()[y * 3 + x] != 0;
+            // The goal is the top row, dotted, and the bar is
+            // a quarter of the way up.
+            assert!(lit(0, 0) && !lit(1, 0) && lit(2, 0));
+            assert!(lit(0, 4) && lit(0, 3) && !lit(0, 2));
+        }
+    }
+}
@@ -0,0 +1,239 @@
+/// Turning rendered frames into what a device should be sent:
+/// dimmed in a perceptual space, corrected for the device's
+/// LEDs, and rounded to bytes, optionally with dithering.
+pub mod color {
+    use crate::animation::animation::Frame;
+    use anyhow::{anyhow, Result};
+    use palette::{FromColor, LinSrgb, Oklab, Srgb};
+    use serde::Deserialize;
+
+    /// Scale a color's light by `light`, from 0 to 1. Lightness is
+    /// scaled by the cube root, which is what OKLab does to light, so
+    /// grays come out as they would scaled in linear RGB. Chroma is
+    /// scaled by the square of that, so as colors dim they also move
+    /// toward gray, keeping their hue, instead of sinking into one
+    /// channel's lowest steps where the panel can't hold the hue.
+    pub fn scale_light(color: Oklab, light: f32) -> Oklab {
+        let lightness = light.clamp(0.0, 1.0).cbrt();
+        let chroma = lightness * lightness;
+        Oklab::new(color.l * lightness, color.a * chroma, color.b * chroma)
+    }
+
+    /// How a device's LEDs differ from an sRGB screen. The defaults
+    /// leave colors alone.
+    #[derive(Clone, Debug, Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    pub struct Calibration {
+        /// The panel's gamma, used instead of the sRGB curve when
+        /// it's given. Higher values keep dim colors brighter.
+        pub gamma: Option<f32>,
+        /// How much of each of red, green and blue to keep, from 0 to
+        /// 1, for panels where white comes out tinted.
+        pub white_balance: [f32; 3],
+        /// Add a 4x4 ordered dither before rounding, so dim gradients
+        /// don't band. The pattern is the same in every frame, so it
+        /// doesn't flicker.
+        pub dither: bool,
+    }
+
+    impl Default for Calibration {
+        fn default() -> Calibration {
+            Calibration {
+                gamma: None,
+                white_balance: [1.0; 3],
+                dither: false,
+            }
+        }
+    }
+
+    /// Thresholds for ordered dithering, from 0 to 15.
+    const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+    impl Calibration {
+        /// Check everything that serde can't.
+        pub fn validate(&self) -> Result<()> {
+            if let Some(gamma) = self.gamma {
+                if !(0.5..=4.0).contains(&gamma) {
+                    return Err(anyhow!("gamma must be between 0.5 and 4, not {}", gamma));
+                }
+            }
+            if let Some(channel) = self
+                .white_balance
+                .iter()
+                .find(|channel| !(0.0..=1.0).contains(*channel))
+            {
+                return Err(anyhow!(
+                    "white_balance values must be between 0 and 1, not {}",
+                    channel
+                ));
+            }
+            Ok(())
+        }
+
+        fn is_identity(&self) -> bool {
+            self.gamma.is_none() && self.white_balance == [1.0; 3] && !self.dither
+        }
+
+        /// Encode a linear channel, from 0 to 1, for the panel.
+        fn encode(&self, linear: f32) -> f32 {
+            let linear = linear.clamp(0.0, 1.0);
+            match self.gamma {
+                Some(gamma) => linear.powf(1.0 / gamma),
+                None => Srgb::<f32>::from_linear(LinSrgb::new(linear, 0.0, 0.0)).red,
+            }
+        }
+
+        /// Round an encoded channel to a byte, nudged by the dither
+        /// threshold for the pixel at `x`, `y` when dithering.
+        fn quantize(&self, value: f32, x: usize, y: usize) -> u8 {
+            let offset = match self.dither {
+                true => (BAYER[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5,
+                false => 0.0,
+            };
+            (value * 255.0 + offset).round().clamp(0.0, 255.0) as u8
+        }
+
+        /// Dim frames that are `width` pixels wide to `light`, from 0
+        /// to 1, along with their own dimming, and correct them for the
+        /// panel. Colors stay in floating point until they're rounded,
+        /// once, at the end.
+        pub fn apply(&self, frames: &[Frame], width: usize, light: f32) -> Vec<Frame> {
+            let undimmed = |frame: &Frame| frame.light.iter().all(|light| *light >= 1.0);
+            if light >= 1.0 && self.is_identity() && frames.iter().all(undimmed) {
+                return frames.to_vec();
+            }
+            let [red, green, blue] = self.white_balance;
+            frames
+                .iter()
+                .map(|frame| {
+                    let mut rgba = Vec::with_capacity(frame.rgba.len());
+                    for (i, pixel) in frame.rgba.chunks_exact(4).enumerate() {
+                        let (x, y) = (i % width.max(1), i / width.max(1));
+                        let srgb = Srgb::new(pixel[0], pixel[1], pixel[2]).into_format::<f32>();
+                        let light = light * frame.light.get(i).copied().unwrap_or(1.0);
+                        let dimmed = scale_light(Oklab::from_color(srgb.into_linear()), light);
+                        let linear = LinSrgb::from_color(dimmed);
+                        rgba.extend([
+                            self.quantize(self.encode(linear.red * red), x, y),
+                            self.quantize(self.encode(linear.green * green), x, y),
+                            self.quantize(self.encode(linear.blue * blue), x, y),
+                            pixel[3],
+                        ]);
+                    }
+                    Frame {
+                        rgba,
+                        light: vec![1.0; frame.light.len()],
+                        duration: frame.duration,
+                    }
+                })
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn close(a: f32, b: f32) -> bool {
+            (a - b).abs() < 1e-4
+        }
+
+        fn oklab(red: u8, green: u8, blue: u8) -> Oklab {
+            Oklab::from_color(
+                Srgb::new(red, green, blue)
+                    .into_format::<f32>()
+                    .into_linear(),
+            )
+        }
+
+        fn frame(pixels: &[[u8; 3]], light: f32) -> Frame {
+            Frame {
+                rgba: pixels
+                    .iter()
+                    .flat_map(|[r, g, b]| [*r, *g, *b, 255])
+                    .collect(),
+                light: vec![light; pixels.len()],
+                duration: 100,
+            }
+        }
+
+        #[test]
+        fn grays_dim_as_light_does() {
+            let gray = LinSrgb::from_color(scale_light(oklab(128, 128, 128), 0.25));
+            let full = Srgb::new(128u8, 128, 128)
+                .into_format::<f32>()
+                .into_linear();
+            assert!(close(gray.red, full.red * 0.25));
+            assert!(close(gray.red, gray.green) && close(gray.green, gray.blue));
+        }
+
+        #[test]
+        fn colors_keep_their_hue_and_lose_chroma() {
+            let orange = oklab(255, 128, 0);
+            let dimmed = scale_light(orange, 0.2);
+            let hue = |color: Oklab| color.b.atan2(color.a);
+            let chroma = |color: Oklab| color.a.hypot(color.b);
+            assert!(close(hue(orange), hue(dimmed)));
+            assert!(chroma(dimmed) / dimmed.l < chroma(orange) / orange.l);
+            // Which isn't what scaling in linear RGB would do.
+            let linear = LinSrgb::from_color(orange) * 0.2;
+            assert!(!close(chroma(Oklab::from_color(linear)), chroma(dimmed)));
+        }
+
+        #[test]
+        fn no_light_is_black() {
+            let black = scale_light(oklab(255, 128, 0), 0.0);
+            assert_eq!((black.l, black.a, black.b), (0.0, 0.0, 0.0));
+        }
+
+        #[test]
+        fn frames_are_rounded_once() {
+            let calibration = Calibration::default();
+            let pixels = [[255, 128, 0], [40, 90, 200]];
+            // A stale widget at 0.4, at a level of 0.5.
+            let stale = calibration.apply(&[frame(&pixels, 0.4)], 2, 0.5);
+            let once = calibration.apply(&[frame(&pixels, 1.0)], 2, 0.2);
+            assert_eq!(stale[0].rgba, once[0].rgba);
+            assert_eq!(stale[0].light, vec![1.0; 2]);
+            let dimmed = LinSrgb::from_color(scale_light(oklab(255, 128, 0), 0.2));
+            let expected = Srgb::<f32>::from_linear(dimmed).into_format::<u8>();
+            assert_eq!(
+                stale[0].rgba[..3],
+                [expected.red, expected.green, expected.blue]
+            );
+        }
+
+        #[test]
+        fn undimmed_frames_are_left_alone() {
+            let calibration = Calibration::default();
+            let frames = [frame(&[[255, 128, 0]], 1.0)];
+            assert_eq!(calibration.apply(&frames, 1, 1.0)[0].rgba, frames[0].rgba);
+            let frames = [frame(&[[255, 128, 0]], 0.4)];
+            assert_ne!(calibration.apply(&frames, 1, 1.0)[0].rgba, frames[0].rgba);
+        }
+
+        #[test]
+        fn dithering_keeps_the_average() {
+            let calibration = Calibration {
+                dither: true,
+                ..Calibration::default()
+            };
+            let frames = [frame(&[[100, 100, 100]; 16], 1.0)];
+            let light = 0.2;
+            let dithered = calibration.apply(&frames, 4, light);
+            let mean = dithered[0]
+                .rgba
+                .chunks_exact(4)
+                .map(|pixel| pixel[0] as f32)
+                .sum::<f32>()
+                / 16.0;
+            let gray = LinSrgb::from_color(scale_light(oklab(100, 100, 100), light));
+            let exact = Srgb::<f32>::from_linear(gray).red * 255.0;
+            assert!((mean - exact).abs() < 1.0 / 16.0, "{} {}", mean, exact);
+            assert!(dithered[0]
+                .rgba
+                .chunks_exact(4)
+                .any(|pixel| pixel[0] != dithered[0].rgba[0]));
+        }
+    }
+}
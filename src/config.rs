@@ -0,0 +1,160 @@
+/// Settings from `~/.tidbyt.json`, all of which have defaults.
+pub mod config {
+    use crate::aqi::aqi::AqiConfig;
+    use crate::brightness::brightness::BrightnessPolicy;
+    use crate::color::color::Calibration;
+    use crate::locale::locale::Locale;
+    use crate::location::location::{parse_timezone, Location};
+    use crate::pusher::pusher::DEFAULT_INSTALLATION;
+    use crate::theme::theme::ThemeConfig;
+    use anyhow::{anyhow, bail, Context, Result};
+    use chrono::format::{Item, StrftimeItems};
+    use serde::Deserialize;
+    use std::path::{Path, PathBuf};
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    pub struct Config {
+        pub location: Location,
+        pub locale: Locale,
+        /// For every device that doesn't have its own.
+        pub brightness: BrightnessPolicy,
+        /// For every device that doesn't have its own.
+        pub calibration: Calibration,
+        pub theme: ThemeConfig,
+        pub aqi: AqiConfig,
+        /// The Tidbyts to drive. Without any, there's one
+        /// from `TIDBYT_ID` and `TIDBYT_KEY`.
+        pub devices: Vec<DeviceConfig>,
+    }
+
+    fn default_installation() -> String {
+        String::from(DEFAULT_INSTALLATION)
+    }
+
+    /// What a device shows.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Shows {
+        /// Whatever `--layout` or `--playlist` says, or the built-in playlist.
+        Default,
+        Layout(PathBuf),
+        Playlist(PathBuf),
+    }
+
+    /// One Tidbyt, with its own credentials and what to show on it.
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct DeviceConfig {
+        pub name: String,
+        pub id: String,
+        pub key: String,
+        layout: Option<PathBuf>,
+        playlist: Option<PathBuf>,
+        #[serde(default = "default_installation")]
+        pub installation: String,
+        #[serde(default)]
+        pub foreground: bool,
+        brightness: Option<BrightnessPolicy>,
+        calibration: Option<Calibration>,
+    }
+
+    impl DeviceConfig {
+        /// The device's own brightness policy, or else the config's.
+        pub fn brightness<'a>(&'a self, config: &'a Config) -> &'a BrightnessPolicy {
+            self.brightness.as_ref().unwrap_or(&config.brightness)
+        }
+
+        /// The device's own calibration, or else the config's.
+        pub fn calibration<'a>(&'a self, config: &'a Config) -> &'a Calibration {
+            self.calibration.as_ref().unwrap_or(&config.calibration)
+        }
+
+        pub fn shows(&self) -> Shows {
+            match (&self.layout, &self.playlist) {
+                (Some(path), _) => Shows::Layout(path.clone()),
+                (None, Some(path)) => Shows::Playlist(path.clone()),
+                (None, None) => Shows::Default,
+            }
+        }
+    }
+
+    /// Where the config lives when `--config` isn't given.
+    pub fn default_config_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|mut path| {
+            path.push(".tidbyt.json");
+            path
+        })
+    }
+
+    impl Config {
+        pub fn parse(json: &str) -> Result<Config> {
+            let config: Config = serde_json::from_str(json).context("Could not parse config")?;
+            let Location { lat, lon, .. } = config.location;
+            if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+                bail!(
+                    "location {}, {} is not a valid latitude and longitude",
+                    lat,
+                    lon
+                );
+            }
+            if let Some(timezone) = &config.location.timezone {
+                parse_timezone(timezone)?;
+            }
+            let date = &config.locale.date;
+            if StrftimeItems::new(date).any(|item| matches!(item, Item::Error)) {
+                bail!("invalid date format `{}`", date);
+            }
+            config
+                .brightness
+                .validate()
+                .context("brightness is invalid")?;
+            config
+                .calibration
+                .validate()
+                .context("calibration is invalid")?;
+            config.theme.theme().context("theme is invalid")?;
+            for (i, device) in config.devices.iter().enumerate() {
+                let fail = |message: String| anyhow!("devices[{}]: {}", i, message);
+                if device.name.is_empty() {
+                    return Err(fail(String::from("name is empty")));
+                }
+                if config.devices[..i].iter().any(|d| d.name == device.name) {
+                    return Err(fail(format!(
+                        "there's already a device named `{}`",
+                        device.name
+                    )));
+                }
+                if device.layout.is_some() && device.playlist.is_some() {
+                    return Err(fail(String::from("has both `layout` and `playlist`")));
+                }
+                if let Some(brightness) = &device.brightness {
+                    brightness
+                        .validate()
+                        .map_err(|err| fail(format!("brightness: {}", err)))?;
+                }
+                if let Some(calibration) = &device.calibration {
+                    calibration
+                        .validate()
+                        .map_err(|err| fail(format!("calibration: {}", err)))?;
+                }
+            }
+            Ok(config)
+        }
+
+        pub fn load(path: &Path) -> Result<Config> {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Could not read config {}", path.display()))?;
+            Config::parse(&json).with_context(|| format!("Invalid config {}", path.display()))
+        }
+
+        /// Load `path` if given, or else the default config
+        /// file if there is one, or else the defaults.
+        pub fn find(path: Option<&Path>) -> Result<Config> {
+            match (path, default_config_path()) {
+                (Some(path), _) => Config::load(path),
+                (None, Some(path)) if path.exists() => Config::load(&path),
+                _ => Ok(Config::default()),
+            }
+        }
+    }
+}
@@ -1,5 +1,82 @@
 pub mod draw_buffer {
-    use raqote::DrawTarget;
+    use crate::animation::animation::Frame;
+    use raqote::{BlendMode, DrawTarget, IntPoint, IntRect, Point};
+    use std::ops::{Deref, DerefMut};
+
+    /// A draw target, and how much light each pixel keeps when it's
+    /// sent, so that widgets can be dimmed without rounding their
+    /// colors twice. Calibration applies it with the brightness.
+    pub struct Canvas {
+        dt: DrawTarget,
+        light: Vec<f32>,
+    }
+
+    impl Canvas {
+        pub fn new(width: i32, height: i32) -> Canvas {
+            Canvas {
+                dt: DrawTarget::new(width, height),
+                light: vec![1.0; (width.max(0) * height.max(0)) as usize],
+            }
+        }
+
+        /// Keep `light`, from 0 to 1, of the box of `size` at `point`,
+        /// on top of any dimming already there.
+        pub fn dim(&mut self, point: Point, size: Point, light: f32) {
+            let (width, height) = (self.dt.width(), self.dt.height());
+            let xs = (point.x.floor() as i32).max(0)..((point.x + size.x).ceil() as i32).min(width);
+            let ys =
+                (point.y.floor() as i32).max(0)..((point.y + size.y).ceil() as i32).min(height);
+            for y in ys {
+                for x in xs.clone() {
+                    self.light[(y * width + x) as usize] *= light;
+                }
+            }
+        }
+
+        /// Draw all of `other` with its top left at `point`,
+        /// along with its dimming.
+        pub fn blend(&mut self, other: &Canvas, point: IntPoint) {
+            let (width, height) = (other.dt.width(), other.dt.height());
+            self.dt.blend_surface(
+                &other.dt,
+                IntRect::new(IntPoint::new(0, 0), IntPoint::new(width, height)),
+                point,
+                BlendMode::SrcOver,
+            );
+            for y in 0..height {
+                for x in 0..width {
+                    let (to_x, to_y) = (point.x + x, point.y + y);
+                    if (0..self.dt.width()).contains(&to_x) && (0..self.dt.height()).contains(&to_y)
+                    {
+                        self.light[(to_y * self.dt.width() + to_x) as usize] *=
+                            other.light[(y * width + x) as usize];
+                    }
+                }
+            }
+        }
+
+        pub fn into_frame(self, duration: u32) -> Frame {
+            Frame {
+                rgba: get_rgba(self.dt),
+                light: self.light,
+                duration,
+            }
+        }
+    }
+
+    impl Deref for Canvas {
+        type Target = DrawTarget;
+
+        fn deref(&self) -> &DrawTarget {
+            &self.dt
+        }
+    }
+
+    impl DerefMut for Canvas {
+        fn deref_mut(&mut self) -> &mut DrawTarget {
+            &mut self.dt
+        }
+    }
 
     pub fn get_rgba(dt: DrawTarget) -> Vec<u8> {
         // let width = dt.width() as u32;
@@ -28,4 +105,28 @@ pub mod draw_buffer {
 
         output
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn dimming_stacks_inside_the_box() {
+            let mut canvas = Canvas::new(4, 2);
+            canvas.dim(Point::new(1.0, 0.0), Point::new(2.0, 1.0), 0.5);
+            canvas.dim(Point::new(2.0, 0.0), Point::new(5.0, 5.0), 0.5);
+            let frame = canvas.into_frame(100);
+            assert_eq!(frame.light, [1.0, 0.5, 0.25, 0.5, 1.0, 1.0, 0.5, 0.5]);
+        }
+
+        #[test]
+        fn blending_brings_the_dimming_along() {
+            let mut inner = Canvas::new(2, 2);
+            inner.dim(Point::new(0.0, 0.0), Point::new(2.0, 2.0), 0.4);
+            let mut canvas = Canvas::new(3, 2);
+            canvas.blend(&inner, IntPoint::new(2, 1));
+            let frame = canvas.into_frame(100);
+            assert_eq!(frame.light, [1.0, 1.0, 1.0, 1.0, 1.0, 0.4]);
+        }
+    }
 }
@@ -1,7 +1,6 @@
 pub mod email {
-    use anyhow::{Context, Result};
-    use cached::proc_macro::cached;
-    use chrono::{DateTime, Utc};
+    use async_trait::async_trait;
+    use chrono::{DateTime, Duration, Utc};
     use jmap_client::client::{Client, Credentials};
     use jmap_client::mailbox::{query::Filter, Role};
     use serde::{Deserialize, Serialize};
@@ -9,6 +8,12 @@ pub mod email {
     use std::fs::File;
     use std::io::{BufReader, BufWriter};
     use std::path::PathBuf;
+    use thiserror::Error;
+
+    use crate::chart::chart::{Chart, Ramp, Series, Stop};
+    use crate::font::font::FontName;
+    use crate::source::source::{DataSource, Reading};
+    use crate::TextWidget;
 
     #[derive(Debug, Serialize, Deserialize)]
     struct Record {
@@ -16,16 +21,69 @@ pub mod email {
         count: u64,
     }
 
-    fn get_email_file_path() -> Result<PathBuf> {
+    /// The inbox's thread count now, and the last few counts for a chart.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Mail {
+        pub count: u64,
+        pub history: Vec<u64>,
+    }
+
+    impl Mail {
+        pub fn text(&self) -> TextWidget {
+            TextWidget {
+                text: format!("{} MAIL", self.count),
+                color: String::from("text"),
+                font: FontName::default(),
+                stale: false,
+            }
+        }
+
+        /// The recent counts as bars, scaled to fit, with
+        /// an empty inbox muted.
+        pub fn chart(&self) -> Chart {
+            let values = self.history.iter().map(|count| *count as f64).collect();
+            Chart::new(Series::new(values).set_ramp(Ramp {
+                stops: vec![
+                    Stop {
+                        at: 0.0,
+                        color: String::from("muted"),
+                    },
+                    Stop {
+                        at: 1.0,
+                        color: String::from("text"),
+                    },
+                ],
+                smooth: false,
+            }))
+        }
+    }
+
+    #[derive(Debug, Error)]
+    pub enum EmailError {
+        #[error("JMAP_TOKEN is not set")]
+        MissingToken,
+        #[error("could not talk to Fastmail: {0}")]
+        Jmap(#[from] jmap_client::Error),
+        #[error("could not find the inbox")]
+        NoInbox,
+        #[error("could not find the home directory for .email.json")]
+        NoHome,
+        #[error("could not save .email.json: {0}")]
+        Io(#[from] std::io::Error),
+        #[error("could not save .email.json: {0}")]
+        Json(#[from] serde_json::Error),
+    }
+
+    fn get_email_file_path() -> Result<PathBuf, EmailError> {
         dirs::home_dir()
             .map(|mut path| {
                 path.push(".email.json");
                 path
             })
-            .context("Could not get email path")
+            .ok_or(EmailError::NoHome)
     }
 
-    fn get_data() -> Result<Vec<Record>> {
+    fn get_data() -> Result<Vec<Record>, EmailError> {
         let path = get_email_file_path()?;
         let file = File::open(path)?;
         let reader = BufReader::new(file);
@@ -33,7 +91,7 @@ pub mod email {
         Ok(u)
     }
 
-    fn save_data(records: &Vec<Record>) -> Result<()> {
+    fn save_data(records: &Vec<Record>) -> Result<(), EmailError> {
         let path = get_email_file_path()?;
         let file = File::create(path)?;
         let writer = BufWriter::new(file);
@@ -41,47 +99,66 @@ pub mod email {
         Ok(())
     }
 
-    /// Get the number of unread threads in my inbox
-    #[cached(time = 120, result = true)]
-    pub async fn get_email_count() -> Result<(u64, Vec<u64>)> {
-        let jmap_token = env::var("JMAP_TOKEN").expect("Missing JMAP_TOKEN");
-        let mut records = get_data().unwrap_or_default();
-
-        let client = Client::new()
-            .credentials(Credentials::bearer(jmap_token))
-            .connect("https://api.fastmail.com/jmap/session")
-            .await?;
-
-        let inbox_id = client
-            .mailbox_query(Filter::role(Role::Inbox).into(), None::<Vec<_>>)
-            .await?
-            .take_ids()
-            .pop()
-            .context("Could not get inbox id")?;
-
-        let inbox = client
-            .mailbox_get(&inbox_id, None::<Vec<_>>)
-            .await?
-            .context("Could not get inbox")?;
-
-        let count = inbox.total_threads() as u64;
-
-        let rec = Record {
-            timestamp: Utc::now(),
-            count,
-        };
-        records.push(rec);
-
-        save_data(&records)?;
-
-        let rec_chart = records
-            .iter()
-            .map(|rec| rec.count)
-            .rev()
-            .take(10)
-            .rev()
-            .collect();
-
-        Ok((count, rec_chart))
+    /// The number of threads in my Fastmail inbox, with a history
+    /// of past counts kept in ~/.email.json.
+    pub struct EmailSource;
+
+    #[async_trait]
+    impl DataSource for EmailSource {
+        type Output = Mail;
+        type Error = EmailError;
+
+        const NAME: &'static str = "mail";
+
+        fn refresh_interval(&self) -> Duration {
+            Duration::minutes(2)
+        }
+
+        // Connecting takes a few round trips, so allow longer.
+        fn timeout(&self) -> Duration {
+            Duration::seconds(20)
+        }
+
+        async fn fetch(&self) -> Result<Reading<Mail>, EmailError> {
+            let jmap_token = env::var("JMAP_TOKEN").map_err(|_| EmailError::MissingToken)?;
+            let mut records = get_data().unwrap_or_default();
+
+            let client = Client::new()
+                .credentials(Credentials::bearer(jmap_token))
+                .connect("https://api.fastmail.com/jmap/session")
+                .await?;
+
+            let inbox_id = client
+                .mailbox_query(Filter::role(Role::Inbox).into(), None::<Vec<_>>)
+                .await?
+                .take_ids()
+                .pop()
+                .ok_or(EmailError::NoInbox)?;
+
+            let inbox = client
+                .mailbox_get(&inbox_id, None::<Vec<_>>)
+                .await?
+                .ok_or(EmailError::NoInbox)?;
+
+            let count = inbox.total_threads() as u64;
+
+            let rec = Record {
+                timestamp: Utc::now(),
+                count,
+            };
+            records.push(rec);
+
+            save_data(&records)?;
+
+            let history = records
+                .iter()
+                .map(|rec| rec.count)
+                .rev()
+                .take(10)
+                .rev()
+                .collect();
+
+            Ok(Reading::Shown(Mail { count, history }))
+        }
     }
 }
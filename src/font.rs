@@ -0,0 +1,364 @@
+/// The bundled BDF fonts, each parsed once into a glyph atlas and shared.
+pub mod font {
+    use anyhow::{anyhow, Context, Result};
+    use bdf::Property;
+    use once_cell::sync::{Lazy, OnceCell};
+    use raqote::{DrawOptions, DrawTarget, Point, SolidSource, Source};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    /// The fonts in `fonts/`, by the name layouts use for them.
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+    pub enum FontName {
+        #[default]
+        #[serde(rename = "tb-8")]
+        Tb8,
+        #[serde(rename = "tom-thumb")]
+        TomThumb,
+        #[serde(rename = "tom-thumb-2")]
+        TomThumb2,
+        #[serde(rename = "cg-pixel-3x5")]
+        CgPixel3x5,
+        #[serde(rename = "cg-pixel-4x5")]
+        CgPixel4x5,
+        #[serde(rename = "dina-6")]
+        Dina6,
+        #[serde(rename = "5x8")]
+        Fixed5x8,
+        #[serde(rename = "6x13")]
+        Fixed6x13,
+        #[serde(rename = "10x20")]
+        Fixed10x20,
+    }
+
+    impl FontName {
+        pub const ALL: [FontName; 9] = [
+            FontName::Tb8,
+            FontName::TomThumb,
+            FontName::TomThumb2,
+            FontName::CgPixel3x5,
+            FontName::CgPixel4x5,
+            FontName::Dina6,
+            FontName::Fixed5x8,
+            FontName::Fixed6x13,
+            FontName::Fixed10x20,
+        ];
+
+        fn bytes(&self) -> &'static [u8] {
+            match self {
+                FontName::Tb8 => include_bytes!("../fonts/tb-8.bdf"),
+                FontName::TomThumb => include_bytes!("../fonts/tom-thumb.bdf"),
+                FontName::TomThumb2 => include_bytes!("../fonts/tom-thumb-2.bdf"),
+                FontName::CgPixel3x5 => include_bytes!("../fonts/CG-pixel-3x5-mono.bdf"),
+                FontName::CgPixel4x5 => include_bytes!("../fonts/CG-pixel-4x5-mono.bdf"),
+                FontName::Dina6 => include_bytes!("../fonts/Dina_r400-6.bdf"),
+                FontName::Fixed5x8 => include_bytes!("../fonts/5x8.bdf"),
+                FontName::Fixed6x13 => include_bytes!("../fonts/6x13.bdf"),
+                FontName::Fixed10x20 => include_bytes!("../fonts/10x20.bdf"),
+            }
+        }
+
+        /// tb-8 has a full-width space, which looks
+        /// too loose on a 64px display, so tighten it.
+        fn space_advance(&self) -> Option<f32> {
+            match self {
+                FontName::Tb8 => Some(2.0),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(Copy, Clone)]
+    pub enum TextAlign {
+        Left,
+        Right,
+    }
+
+    /// A glyph rasterized into a bitmap, so drawing it is a
+    /// blit instead of a `fill_rect` per pixel.
+    struct RasterGlyph {
+        width: i32,
+        height: i32,
+        bits: Vec<bool>,
+        /// Offset from the pen position to the left of the bitmap.
+        x: i32,
+        /// Pixels from the top of the bitmap down to the baseline.
+        rise: i32,
+        advance: f32,
+    }
+
+    impl RasterGlyph {
+        fn new(glyph: &bdf::Glyph, advance: f32) -> RasterGlyph {
+            let bounds = glyph.bounds();
+            // Index the bitmap directly: collecting `pixels()` hits an
+            // overflow in the bdf crate's `size_hint`.
+            let mut bits = Vec::with_capacity((glyph.width() * glyph.height()) as usize);
+            for y in 0..glyph.height() {
+                for x in 0..glyph.width() {
+                    bits.push(glyph.get(x, y));
+                }
+            }
+            RasterGlyph {
+                width: glyph.width() as i32,
+                height: glyph.height() as i32,
+                bits,
+                x: bounds.x,
+                rise: bounds.height as i32 + bounds.y,
+                advance,
+            }
+        }
+
+        /// Copy the glyph into the target's pixels with its top left at
+        /// `(left, top)`, blending `color` over what's already there.
+        fn blit(&self, dt: &mut DrawTarget, left: i32, top: i32, color: &SolidSource) {
+            let (width, height) = (dt.width(), dt.height());
+            let pixel = color.to_u32();
+            let inverse_alpha = 255 - color.a as u32;
+            let data = dt.get_data_mut();
+            for y in 0..self.height {
+                let dy = top + y;
+                if dy < 0 || dy >= height {
+                    continue;
+                }
+                for x in 0..self.width {
+                    let dx = left + x;
+                    if dx < 0 || dx >= width || !self.bits[(y * self.width + x) as usize] {
+                        continue;
+                    }
+                    let dest = &mut data[(dy * width + dx) as usize];
+                    *dest = if inverse_alpha == 0 {
+                        pixel
+                    } else {
+                        // Colors are premultiplied, so source-over is
+                        // the source plus what's left of the destination.
+                        let channel = |shift: u32| {
+                            let d = (*dest >> shift) & 0xff;
+                            (((pixel >> shift) & 0xff) + d * inverse_alpha / 255) << shift
+                        };
+                        channel(24) | channel(16) | channel(8) | channel(0)
+                    };
+                }
+            }
+        }
+
+        /// Draw the glyph a pixel at a time, for sources
+        /// like gradients that can't be blitted.
+        fn fill(&self, dt: &mut DrawTarget, left: i32, top: i32, color: &Source) {
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    if self.bits[(y * self.width + x) as usize] {
+                        dt.fill_rect(
+                            (left + x) as f32,
+                            (top + y) as f32,
+                            1.,
+                            1.,
+                            color,
+                            &DrawOptions::new(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// A font rasterized into a glyph atlas, with the
+    /// metrics needed to lay it out.
+    pub struct Font {
+        name: FontName,
+        glyphs: HashMap<char, RasterGlyph>,
+        /// Pixels from the top of a line to the baseline.
+        pub ascent: f32,
+        /// Pixels from the baseline to the bottom of a line.
+        pub descent: f32,
+    }
+
+    fn integer_property(font: &bdf::Font, key: &str) -> Option<f32> {
+        match font.properties().get(key) {
+            Some(Property::Integer(value)) => Some(*value as f32),
+            _ => None,
+        }
+    }
+
+    /// FontForge exports glyphs that have no codepoint as
+    /// `ENCODING -1`, which the bdf crate can't read. We can't
+    /// draw those anyway, so drop them before parsing.
+    fn without_unencoded(bytes: &[u8]) -> Vec<u8> {
+        let text = String::from_utf8_lossy(bytes);
+        let mut output = String::with_capacity(text.len());
+        let mut glyph = String::new();
+        let mut in_glyph = false;
+        for line in text.lines() {
+            if line.starts_with("STARTCHAR") {
+                in_glyph = true;
+            }
+            if in_glyph {
+                glyph.push_str(line);
+                glyph.push('\n');
+                if line.starts_with("ENDCHAR") {
+                    if !glyph.contains("\nENCODING -") {
+                        output.push_str(&glyph);
+                    }
+                    glyph.clear();
+                    in_glyph = false;
+                }
+            } else {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+        output.into_bytes()
+    }
+
+    impl Font {
+        fn parse(name: FontName) -> Result<Font> {
+            let bdf = bdf::read(&without_unencoded(name.bytes())[..])
+                .with_context(|| format!("Could not parse {:?}", name))?;
+            let bounds = *bdf.bounds();
+            let ascent = integer_property(&bdf, "FONT_ASCENT")
+                .unwrap_or((bounds.height as i32 + bounds.y) as f32);
+            let descent = integer_property(&bdf, "FONT_DESCENT").unwrap_or(-bounds.y as f32);
+            let glyphs = bdf
+                .glyphs()
+                .iter()
+                .map(|(c, glyph)| {
+                    let advance = match (c, name.space_advance(), glyph.device_width()) {
+                        (' ', Some(space), _) => space,
+                        (_, _, Some((x, _))) => *x as f32,
+                        _ => glyph.width() as f32,
+                    };
+                    (*c, RasterGlyph::new(glyph, advance))
+                })
+                .collect();
+            Ok(Font {
+                name,
+                glyphs,
+                ascent,
+                descent,
+            })
+        }
+
+        pub fn height(&self) -> f32 {
+            self.ascent + self.descent
+        }
+
+        /// The glyph for `c`, taken from tb-8 when this font doesn't
+        /// have it, like `°` in the cg-pixel fonts. Characters that
+        /// neither has are left out.
+        fn glyph(&self, c: char) -> Option<&RasterGlyph> {
+            self.glyphs.get(&c).or_else(|| match self.name {
+                FontName::Tb8 => None,
+                _ => font(FontName::Tb8).ok()?.glyphs.get(&c),
+            })
+        }
+
+        pub fn advance(&self, c: char) -> f32 {
+            self.glyph(c).map(|g| g.advance).unwrap_or(0.0)
+        }
+
+        pub fn width(&self, text: &str) -> f32 {
+            text.chars().map(|c| self.advance(c)).sum()
+        }
+
+        /// Draw text with the top of its line at `in_start`, placing
+        /// each glyph relative to the font's baseline. Solid colors are
+        /// blitted straight into the pixel buffer, which skips raqote's
+        /// clip stack, so clip by drawing into a smaller target.
+        pub fn draw(
+            &self,
+            dt: &mut DrawTarget,
+            text: &str,
+            in_start: Point,
+            color: &Source,
+            align: TextAlign,
+        ) -> Result<()> {
+            let mut x = in_start.x.round() as i32;
+            let baseline = (in_start.y + self.ascent).round() as i32;
+            let chars: Vec<char> = match align {
+                TextAlign::Left => text.chars().collect(),
+                TextAlign::Right => text.chars().rev().collect(),
+            };
+            for c in chars {
+                let Some(glyph) = self.glyph(c) else {
+                    continue;
+                };
+                let (left, top) = (x + glyph.x, baseline - glyph.rise);
+                match color {
+                    Source::Solid(solid) => glyph.blit(dt, left, top, solid),
+                    _ => glyph.fill(dt, left, top, color),
+                }
+                x += glyph.advance as i32
+                    * match align {
+                        TextAlign::Left => 1,
+                        TextAlign::Right => -1,
+                    }
+            }
+            Ok(())
+        }
+    }
+
+    /// Parses each font the first time it's asked for,
+    /// so unused large fonts like 10x20 cost nothing.
+    pub struct FontRegistry {
+        fonts: HashMap<FontName, OnceCell<Font>>,
+    }
+
+    impl FontRegistry {
+        fn new() -> FontRegistry {
+            FontRegistry {
+                fonts: FontName::ALL
+                    .iter()
+                    .map(|name| (*name, OnceCell::new()))
+                    .collect(),
+            }
+        }
+
+        pub fn get(&self, name: FontName) -> Result<&Font> {
+            self.fonts
+                .get(&name)
+                .ok_or_else(|| anyhow!("Font {:?} is not registered", name))?
+                .get_or_try_init(|| Font::parse(name))
+        }
+    }
+
+    static REGISTRY: Lazy<FontRegistry> = Lazy::new(FontRegistry::new);
+
+    pub fn font(name: FontName) -> Result<&'static Font> {
+        REGISTRY.get(name)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn missing_glyphs_come_from_tb8() {
+            let small = font(FontName::CgPixel3x5).unwrap();
+            let tb8 = font(FontName::Tb8).unwrap();
+            assert!(!small.glyphs.contains_key(&'°'));
+            assert_eq!(small.width("72°"), small.width("72") + tb8.advance('°'));
+            let mut dt = DrawTarget::new(16, 8);
+            let white = Source::Solid(SolidSource::from_unpremultiplied_argb(255, 255, 255, 255));
+            small
+                .draw(&mut dt, "72°", Point::new(0., 0.), &white, TextAlign::Left)
+                .unwrap();
+            let degree_x = small.width("72") as usize;
+            assert!((0..8).any(|y| (degree_x..16).any(|x| dt.get_data()[y * 16 + x] != 0)));
+        }
+
+        #[test]
+        fn glyphs_no_font_has_are_skipped() {
+            let tb8 = font(FontName::Tb8).unwrap();
+            assert_eq!(tb8.width("a\u{1f600}b"), tb8.width("ab"));
+            let mut dt = DrawTarget::new(16, 8);
+            let white = Source::Solid(SolidSource::from_unpremultiplied_argb(255, 255, 255, 255));
+            tb8.draw(
+                &mut dt,
+                "a\u{1f600}b",
+                Point::new(0., 0.),
+                &white,
+                TextAlign::Left,
+            )
+            .unwrap();
+        }
+    }
+}
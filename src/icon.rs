@@ -0,0 +1,158 @@
+/// Small pixel-art icons, drawn a pixel at a time.
+pub mod icon {
+    use crate::adjusted_color::adjusted_color::adjusted_color;
+    use anyhow::Result;
+    use raqote::{DrawOptions, DrawTarget, Point};
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum Icon {
+        Sun,
+        Moon,
+        SunAndCloud,
+        MoonAndCloud,
+        Cloud,
+        Rain,
+        Snow,
+        Thunder,
+        /// A raindrop, for the chance of precipitation.
+        Drop,
+    }
+
+    /// The theme's color for each letter in a bitmap.
+    /// Dots are transparent.
+    fn palette(pixel: char) -> Option<&'static str> {
+        match pixel {
+            'y' => Some("icon.sun"),
+            'o' => Some("icon.rays"),
+            'm' => Some("icon.moon"),
+            'w' => Some("icon.cloud"),
+            'g' => Some("icon.storm"),
+            'b' => Some("precip"),
+            's' => Some("icon.snow"),
+            _ => None,
+        }
+    }
+
+    impl Icon {
+        fn bitmap(&self) -> &'static [&'static str] {
+            match self {
+                Icon::Sun => &[
+                    "....o....",
+                    ".o.....o.",
+                    "...yyy...",
+                    "..yyyyy..",
+                    "o.yyyyy.o",
+                    "..yyyyy..",
+                    "...yyy...",
+                    ".o.....o.",
+                    "....o....",
+                ],
+                Icon::Moon => &[
+                    "..mmm....",
+                    ".mm......",
+                    "mm.......",
+                    "mm.......",
+                    "mm.......",
+                    "mm......m",
+                    ".mm....mm",
+                    "..mmmmmm.",
+                    "...mmm...",
+                ],
+                Icon::SunAndCloud => &[
+                    "......o..",
+                    "....yyy.o",
+                    "...yyyyy.",
+                    "..wwyyyy.",
+                    ".wwwwyy..",
+                    "wwwwwww..",
+                    "wwwwwwww.",
+                    ".wwwwww..",
+                    ".........",
+                ],
+                Icon::MoonAndCloud => &[
+                    ".....mm..",
+                    "....mm...",
+                    "...mm....",
+                    "..wwm...m",
+                    ".wwwwmmm.",
+                    "wwwwwww..",
+                    "wwwwwwww.",
+                    ".wwwwww..",
+                    ".........",
+                ],
+                Icon::Cloud => &[
+                    ".........",
+                    ".........",
+                    "...ww....",
+                    "..wwww.w.",
+                    ".wwwwwwww",
+                    "wwwwwwwww",
+                    "wwwwwwwww",
+                    ".wwwwwww.",
+                    ".........",
+                ],
+                Icon::Rain => &[
+                    "...gg....",
+                    "..gggg.g.",
+                    ".gggggggg",
+                    "ggggggggg",
+                    ".ggggggg.",
+                    ".........",
+                    ".b..b..b.",
+                    "b..b..b..",
+                    ".........",
+                ],
+                Icon::Snow => &[
+                    "...ww....",
+                    "..wwww.w.",
+                    ".wwwwwwww",
+                    "wwwwwwwww",
+                    ".wwwwwww.",
+                    ".........",
+                    ".s...s...",
+                    "...s...s.",
+                    ".s...s...",
+                ],
+                Icon::Thunder => &[
+                    "...gg....",
+                    "..gggg.g.",
+                    ".gggggggg",
+                    "ggggggggg",
+                    ".ggyggg..",
+                    "...yy....",
+                    "..yy.....",
+                    "...y.....",
+                    "..y......",
+                ],
+                Icon::Drop => &[".b.", ".b.", "bbb", "bbb", ".b."],
+            }
+        }
+
+        pub fn width(&self) -> f32 {
+            self.bitmap()[0].len() as f32
+        }
+
+        pub fn height(&self) -> f32 {
+            self.bitmap().len() as f32
+        }
+
+        /// Draw the icon with its top left at `point`.
+        pub fn draw(&self, dt: &mut DrawTarget, point: Point) -> Result<()> {
+            for (y, row) in self.bitmap().iter().enumerate() {
+                for (x, pixel) in row.chars().enumerate() {
+                    if let Some(hex) = palette(pixel) {
+                        dt.fill_rect(
+                            point.x + x as f32,
+                            point.y + y as f32,
+                            1.0,
+                            1.0,
+                            &adjusted_color(hex)?,
+                            &DrawOptions::new(),
+                        );
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,785 @@
+/// Declarative layouts, loaded from JSON and turned into a tree of widgets.
+pub mod layout {
+    use crate::aqi::aqi::{Aqi, AqiSource, Pollutant};
+    use crate::chart::chart::{Chart, ChartStyle};
+    use crate::email::email::{EmailSource, Mail};
+    use crate::font::font::FontName;
+    use crate::locale::locale::Locale;
+    use crate::source::source::{DataSource, Reading, Readings};
+    use crate::strava::strava::{Runs, StravaSource};
+    use crate::theme::theme::resolve;
+    use crate::timeline::timeline::{Schedule, DEFAULT_FRAME_DURATION};
+    use crate::uv::uv::{Uv, UvHour, UvSource};
+    use crate::weather::weather::{Condition, Hour, Weather, WeatherSource, FORECAST_HOURS};
+    use crate::{
+        ChartWidget, ErrorWidget, HStack, MarqueeWidget, TextWidget, VStack, WeatherWidget, Widget,
+        WIDTH,
+    };
+    use anyhow::{anyhow, bail, Context, Result};
+    use chrono::format::{Item, StrftimeItems};
+    use chrono::{DateTime, Duration, Local, Utc};
+    use once_cell::sync::OnceCell;
+    use serde::Deserialize;
+    use serde_json::Value;
+    use std::path::Path;
+
+    /// The layout that ships with the binary, used when no
+    /// `--layout` file is given.
+    pub const DEFAULT_LAYOUT: &str = include_str!("../layouts/default.json");
+
+    fn default_color() -> String {
+        String::from("text")
+    }
+
+    fn default_marquee_width() -> f32 {
+        WIDTH as f32
+    }
+
+    fn default_marquee_speed() -> u32 {
+        1
+    }
+
+    fn default_frame_duration() -> u32 {
+        DEFAULT_FRAME_DURATION
+    }
+
+    fn default_marquee_pause() -> u32 {
+        1000
+    }
+
+    fn default_marquee_gap() -> f32 {
+        8.0
+    }
+
+    fn default_expand() -> bool {
+        true
+    }
+
+    fn default_forecast_hours() -> usize {
+        FORECAST_HOURS
+    }
+
+    /// Where the EPA recommends sun protection from.
+    fn default_uv_threshold() -> i32 {
+        3
+    }
+
+    /// A node in a layout file. Every node has a `type` tag.
+    #[derive(Debug, Deserialize)]
+    #[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+    pub enum Node {
+        VStack {
+            #[serde(default)]
+            gap: f32,
+            #[serde(default)]
+            schedule: Schedule,
+            items: Vec<Node>,
+        },
+        /// Items in a row, spread across the whole width
+        /// unless `expand` is false.
+        HStack {
+            #[serde(default)]
+            gap: f32,
+            #[serde(default = "default_expand")]
+            expand: bool,
+            #[serde(default)]
+            schedule: Schedule,
+            items: Vec<Node>,
+        },
+        /// Either static `text`, or the text for a named `source`.
+        /// Source text is dimmed once it's older than `stale_after`
+        /// seconds, or the source's own limit if that's not given.
+        Text {
+            text: Option<String>,
+            source: Option<String>,
+            color: Option<String>,
+            #[serde(default)]
+            font: FontName,
+            stale_after: Option<u64>,
+        },
+        /// Text that scrolls within `width` when it doesn't fit.
+        Marquee {
+            text: Option<String>,
+            source: Option<String>,
+            color: Option<String>,
+            #[serde(default)]
+            font: FontName,
+            #[serde(default = "default_marquee_width")]
+            width: f32,
+            #[serde(default = "default_marquee_speed")]
+            speed: u32,
+            #[serde(default = "default_frame_duration")]
+            frame_duration: u32,
+            #[serde(default = "default_marquee_pause")]
+            pause_start: u32,
+            #[serde(default = "default_marquee_pause")]
+            pause_end: u32,
+            #[serde(default)]
+            wrap: bool,
+            #[serde(default = "default_marquee_gap")]
+            gap: f32,
+            stale_after: Option<u64>,
+        },
+        /// The time, in the locale's 12 or 24 hour format
+        /// unless `format` is given.
+        Clock {
+            format: Option<String>,
+            #[serde(default = "default_color")]
+            color: String,
+            #[serde(default)]
+            font: FontName,
+        },
+        /// Today's date, in the locale's format unless `format` is given.
+        Date {
+            format: Option<String>,
+            #[serde(default = "default_color")]
+            color: String,
+            #[serde(default)]
+            font: FontName,
+        },
+        Chart {
+            source: String,
+            stale_after: Option<u64>,
+            #[serde(default)]
+            style: ChartStyle,
+        },
+        /// The conditions icon and temperature, with
+        /// the day's high and low and precipitation.
+        Weather { stale_after: Option<u64> },
+        /// A sparkline of the temperature and chance of
+        /// precipitation for the next `hours` hours.
+        Forecast {
+            #[serde(default = "default_forecast_hours")]
+            hours: usize,
+            stale_after: Option<u64>,
+            #[serde(default)]
+            style: ChartStyle,
+        },
+        /// The UV index for the rest of today's forecast, marking
+        /// the current hour and the hours at or above `threshold`.
+        Uv {
+            #[serde(default = "default_uv_threshold")]
+            threshold: i32,
+            stale_after: Option<u64>,
+            #[serde(default)]
+            style: ChartStyle,
+        },
+    }
+
+    /// Sources that can feed a text node.
+    pub(crate) const TEXT_SOURCES: [&str; 6] =
+        ["weather", "aqi", "uv", "mail", "run_today", "run_week"];
+
+    /// Sources that can feed a chart node.
+    const CHART_SOURCES: [&str; 2] = ["mail", "run_week"];
+
+    /// Everything fetched for one render, which layout
+    /// nodes pull their values from.
+    pub struct Data {
+        pub now: DateTime<Local>,
+        pub readings: Readings,
+        pub locale: Locale,
+    }
+
+    impl Data {
+        /// Fixed values for every source, for benchmarking
+        /// and trying out layouts without any credentials.
+        pub fn sample(now: DateTime<Local>) -> Result<Data> {
+            let mut readings = Readings::default();
+            readings.insert::<WeatherSource>(Reading::Shown(Weather {
+                temperature: 72,
+                condition: Condition::Rain,
+                daytime: true,
+                high: 75,
+                low: 61,
+                precipitation: 70,
+                hourly: (0..FORECAST_HOURS as i32)
+                    .map(|hour| Hour {
+                        temperature: 72 - (hour - 3).abs(),
+                        precipitation: (70 - hour * 10).max(0),
+                    })
+                    .collect(),
+            }))?;
+            readings.insert::<AqiSource>(Reading::Shown(Aqi {
+                aqi: 124,
+                pollutant: Pollutant::Pm25,
+            }))?;
+            let timezone = chrono_tz::UTC;
+            let morning = now
+                .with_timezone(&timezone)
+                .date_naive()
+                .and_hms_opt(7, 0, 0)
+                .unwrap();
+            readings.insert::<UvSource>(Reading::Shown(Uv {
+                timezone,
+                peak: 7,
+                hours: [0, 1, 2, 4, 5, 6, 7, 7, 6, 4, 3, 1, 0]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, index)| UvHour {
+                        time: morning + Duration::hours(i as i64),
+                        index: *index,
+                    })
+                    .collect(),
+            }))?;
+            readings.insert::<EmailSource>(Reading::Shown(Mail {
+                count: 14,
+                history: vec![9, 10, 12, 12, 11, 13, 15, 14, 14, 14],
+            }))?;
+            readings.insert::<StravaSource>(Reading::Shown(Runs {
+                today_meters: Some(6759.0),
+                week_meters: 20278.0,
+                day_meters: vec![6437.0, 0.0, 8047.0, 4828.0],
+            }))?;
+            Ok(Data {
+                now,
+                readings,
+                locale: Locale::default(),
+            })
+        }
+    }
+
+    /// A layout that has been parsed and checked, ready to
+    /// be built into widgets on each render.
+    #[derive(Debug)]
+    pub struct Layout {
+        root: Node,
+    }
+
+    impl Node {
+        fn kind(&self) -> &'static str {
+            match self {
+                Node::VStack { .. } => "vstack",
+                Node::HStack { .. } => "hstack",
+                Node::Text { .. } => "text",
+                Node::Marquee { .. } => "marquee",
+                Node::Clock { .. } => "clock",
+                Node::Date { .. } => "date",
+                Node::Chart { .. } => "chart",
+                Node::Weather { .. } => "weather",
+                Node::Forecast { .. } => "forecast",
+                Node::Uv { .. } => "uv",
+            }
+        }
+
+        /// Check everything that serde can't, so that a broken
+        /// layout fails at startup rather than at render time.
+        fn validate(&self, path: &str) -> Result<()> {
+            let fail = |message: String| anyhow!("{} ({}): {}", path, self.kind(), message);
+            match self {
+                Node::VStack {
+                    items, schedule, ..
+                }
+                | Node::HStack {
+                    items, schedule, ..
+                } => {
+                    if items.is_empty() {
+                        return Err(fail(String::from("stack has no items")));
+                    }
+                    schedule.validate().map_err(|err| fail(err.to_string()))?;
+                    for (i, item) in items.iter().enumerate() {
+                        item.validate(&format!("{}.items[{}]", path, i))?;
+                    }
+                }
+                Node::Text {
+                    text,
+                    source,
+                    color,
+                    stale_after,
+                    ..
+                } => {
+                    validate_text(text, source, color).map_err(fail)?;
+                    validate_stale_after(stale_after).map_err(fail)?;
+                }
+                Node::Marquee {
+                    text,
+                    source,
+                    color,
+                    width,
+                    speed,
+                    frame_duration,
+                    stale_after,
+                    ..
+                } => {
+                    validate_text(text, source, color).map_err(fail)?;
+                    validate_stale_after(stale_after).map_err(fail)?;
+                    if *frame_duration == 0 {
+                        return Err(fail(String::from("frame_duration must be at least 1ms")));
+                    }
+                    if *width < 1.0 {
+                        return Err(fail(format!("width must be at least 1, not {}", width)));
+                    }
+                    if *speed == 0 {
+                        return Err(fail(String::from("speed must be at least 1")));
+                    }
+                }
+                Node::Clock { format, color, .. } | Node::Date { format, color, .. } => {
+                    if let Some(format) = format {
+                        if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                            return Err(fail(format!("invalid format `{}`", format)));
+                        }
+                    }
+                    resolve(color).map_err(|err| fail(err.to_string()))?;
+                }
+                Node::Chart {
+                    source,
+                    stale_after,
+                    style,
+                } => {
+                    style.validate().map_err(|err| fail(err.to_string()))?;
+                    if !CHART_SOURCES.contains(&source.as_str()) {
+                        return Err(fail(format!(
+                            "unknown source `{}`, expected one of {}",
+                            source,
+                            CHART_SOURCES.join(", ")
+                        )));
+                    }
+                    validate_stale_after(stale_after).map_err(fail)?;
+                }
+                Node::Weather { stale_after } => {
+                    validate_stale_after(stale_after).map_err(fail)?;
+                }
+                Node::Forecast {
+                    hours,
+                    stale_after,
+                    style,
+                } => {
+                    style.validate().map_err(|err| fail(err.to_string()))?;
+                    if *hours == 0 || *hours > FORECAST_HOURS {
+                        return Err(fail(format!(
+                            "hours must be between 1 and {}, not {}",
+                            FORECAST_HOURS, hours
+                        )));
+                    }
+                    validate_stale_after(stale_after).map_err(fail)?;
+                }
+                Node::Uv {
+                    threshold,
+                    stale_after,
+                    style,
+                } => {
+                    style.validate().map_err(|err| fail(err.to_string()))?;
+                    if !(1..=11).contains(threshold) {
+                        return Err(fail(format!(
+                            "threshold must be between 1 and 11, not {}",
+                            threshold
+                        )));
+                    }
+                    validate_stale_after(stale_after).map_err(fail)?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Build this node into a widget. Sources with nothing to
+        /// show, like a normal AQI, build to `None` and are left out.
+        fn build(&self, data: &Data) -> Result<Option<Box<dyn Widget>>> {
+            Ok(match self {
+                Node::VStack {
+                    gap,
+                    schedule,
+                    items,
+                } => Some(Box::new(VStack {
+                    items: build_items(items, data)?,
+                    gap: *gap,
+                    schedule: schedule.clone(),
+                    timeline: OnceCell::new(),
+                })),
+                Node::HStack {
+                    gap,
+                    expand,
+                    schedule,
+                    items,
+                } => Some(Box::new(HStack {
+                    items: build_items(items, data)?,
+                    gap: *gap,
+                    expand: *expand,
+                    schedule: schedule.clone(),
+                    timeline: OnceCell::new(),
+                })),
+                Node::Text { source, .. } | Node::Marquee { source, .. }
+                    if source_failed(source.as_deref(), data) =>
+                {
+                    Some(Box::new(ErrorWidget))
+                }
+                Node::Chart { source, .. } if source_failed(Some(source), data) => {
+                    Some(Box::new(ErrorWidget))
+                }
+                Node::Weather { .. } | Node::Forecast { .. }
+                    if source_failed(Some("weather"), data) =>
+                {
+                    Some(Box::new(ErrorWidget))
+                }
+                Node::Uv { .. } if source_failed(Some("uv"), data) => Some(Box::new(ErrorWidget)),
+                Node::Text {
+                    text,
+                    source,
+                    color,
+                    font,
+                    stale_after,
+                } => build_text(text, source, color, *font, *stale_after, data)?
+                    .map(|widget| Box::new(widget) as Box<dyn Widget>),
+                Node::Marquee {
+                    text,
+                    source,
+                    color,
+                    font,
+                    width,
+                    speed,
+                    frame_duration,
+                    pause_start,
+                    pause_end,
+                    wrap,
+                    gap,
+                    stale_after,
+                } => match build_text(text, source, color, *font, *stale_after, data)? {
+                    Some(widget) => Some(Box::new(
+                        MarqueeWidget::new(widget, *width)?
+                            .set_speed(*speed, *frame_duration)
+                            .set_pauses(*pause_start, *pause_end)
+                            .set_wrap(*wrap, *gap),
+                    )),
+                    None => None,
+                },
+                Node::Clock {
+                    format,
+                    color,
+                    font,
+                } => {
+                    let format = format.as_deref().unwrap_or(data.locale.time_format());
+                    Some(Box::new(
+                        TextWidget::new(format!("{}", data.now.format(format)), color.clone())?
+                            .set_font(*font),
+                    ))
+                }
+                Node::Date {
+                    format,
+                    color,
+                    font,
+                } => {
+                    let format = format.as_deref().unwrap_or(&data.locale.date);
+                    Some(Box::new(
+                        TextWidget::new(format!("{}", data.now.format(format)), color.clone())?
+                            .set_font(*font),
+                    ))
+                }
+                Node::Chart {
+                    source,
+                    stale_after,
+                    style,
+                } => {
+                    let chart = match source.as_str() {
+                        "mail" => data.readings.get::<EmailSource>().map(|mail| mail.chart()),
+                        _ => data
+                            .readings
+                            .get::<StravaSource>()
+                            .map(|runs| runs.chart(data.locale.units)),
+                    };
+                    chart.map(|chart| {
+                        chart_widget(style.apply(chart), source_stale(source, *stale_after, data))
+                    })
+                }
+                Node::Weather { stale_after } => {
+                    data.readings.get::<WeatherSource>().map(|weather| {
+                        Box::new(
+                            WeatherWidget::new(weather, data.locale.units).set_stale(source_stale(
+                                "weather",
+                                *stale_after,
+                                data,
+                            )),
+                        ) as Box<dyn Widget>
+                    })
+                }
+                Node::Forecast {
+                    hours,
+                    stale_after,
+                    style,
+                } => data.readings.get::<WeatherSource>().map(|weather| {
+                    chart_widget(
+                        style.apply(weather.forecast_chart(*hours, data.locale.units)),
+                        source_stale("weather", *stale_after, data),
+                    )
+                }),
+                Node::Uv {
+                    threshold,
+                    stale_after,
+                    style,
+                } => data
+                    .readings
+                    .get::<UvSource>()
+                    .and_then(|uv| uv.chart(data.now.with_timezone(&Utc), *threshold))
+                    .map(|chart| {
+                        chart_widget(style.apply(chart), source_stale("uv", *stale_after, data))
+                    }),
+            })
+        }
+    }
+
+    /// A chart, or an error in its place if the values are
+    /// no good, so that one bad reading doesn't fail the frame.
+    fn chart_widget(chart: Chart, stale: bool) -> Box<dyn Widget> {
+        match chart.validate() {
+            Ok(()) => Box::new(ChartWidget::new(chart).set_stale(stale)),
+            Err(err) => {
+                println!("Could not draw a chart: {:#}", err);
+                Box::new(ErrorWidget)
+            }
+        }
+    }
+
+    /// Deserialize a node, and if it fails, find the innermost node
+    /// responsible so that the error can point at it.
+    fn deserialize_node(value: Value, path: &str) -> Result<Node> {
+        match Node::deserialize(&value) {
+            Ok(node) => Ok(node),
+            Err(err) => {
+                if let Some(items) = value.get("items").and_then(Value::as_array) {
+                    for (i, item) in items.iter().enumerate() {
+                        deserialize_node(item.clone(), &format!("{}.items[{}]", path, i))?;
+                    }
+                }
+                let kind = value.get("type").and_then(Value::as_str).unwrap_or("?");
+                Err(anyhow!("{} ({}): {}", path, kind, err))
+            }
+        }
+    }
+
+    fn validate_stale_after(stale_after: &Option<u64>) -> Result<(), String> {
+        match stale_after {
+            Some(0) => Err(String::from("stale_after must be at least 1 second")),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_text(
+        text: &Option<String>,
+        source: &Option<String>,
+        color: &Option<String>,
+    ) -> Result<(), String> {
+        match (text, source) {
+            (Some(_), Some(_)) => return Err(String::from("has both `text` and `source`")),
+            (None, None) => return Err(String::from("needs either `text` or `source`")),
+            (None, Some(source)) if !TEXT_SOURCES.contains(&source.as_str()) => {
+                return Err(format!(
+                    "unknown source `{}`, expected one of {}",
+                    source,
+                    TEXT_SOURCES.join(", ")
+                ))
+            }
+            _ => {}
+        }
+        if let Some(color) = color {
+            resolve(color).map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// The text for a text or marquee node, either static
+    /// or from its source, with any overrides applied.
+    fn build_text(
+        text: &Option<String>,
+        source: &Option<String>,
+        color: &Option<String>,
+        font: FontName,
+        stale_after: Option<u64>,
+        data: &Data,
+    ) -> Result<Option<TextWidget>> {
+        let widget = match (text, source) {
+            (Some(text), _) => Some(TextWidget::new(text.clone(), default_color())?),
+            (None, Some(source)) => text_source(source, data)?
+                .map(|widget| widget.set_stale(source_stale(source, stale_after, data))),
+            (None, None) => None,
+        };
+        Ok(widget.map(|mut widget| {
+            if let Some(color) = color {
+                widget.color = color.clone();
+            }
+            widget.set_font(font)
+        }))
+    }
+
+    fn build_items(items: &[Node], data: &Data) -> Result<Vec<Box<dyn Widget>>> {
+        let mut widgets = Vec::new();
+        for item in items {
+            if let Some(widget) = item.build(data)? {
+                widgets.push(widget);
+            }
+        }
+        Ok(widgets)
+    }
+
+    /// Whether a text source has anything to show, like an AQI that's
+    /// bad enough to mention.
+    pub(crate) fn source_shown(source: &str, data: &Data) -> Result<bool> {
+        Ok(text_source(source, data)?.is_some())
+    }
+
+    /// The data source behind a text or chart source.
+    fn source_name(source: &str) -> &'static str {
+        match source {
+            "weather" => WeatherSource::NAME,
+            "aqi" => AqiSource::NAME,
+            "uv" => UvSource::NAME,
+            "mail" => EmailSource::NAME,
+            _ => StravaSource::NAME,
+        }
+    }
+
+    fn source_stale(source: &str, stale_after: Option<u64>, data: &Data) -> bool {
+        data.readings.stale(
+            source_name(source),
+            data.now.with_timezone(&Utc),
+            stale_after.map(|seconds| Duration::seconds(seconds as i64)),
+        )
+    }
+
+    /// Whether a node's source has never fetched successfully,
+    /// so it should show an error instead.
+    fn source_failed(source: Option<&str>, data: &Data) -> bool {
+        source
+            .map(|source| data.readings.failed(source_name(source)))
+            .unwrap_or(false)
+    }
+
+    /// Text for a named source, or `None` if the source is
+    /// hidden or hasn't been fetched.
+    fn text_source(source: &str, data: &Data) -> Result<Option<TextWidget>> {
+        let readings = &data.readings;
+        let units = data.locale.units;
+        Ok(match source {
+            "weather" => readings
+                .get::<WeatherSource>()
+                .map(|weather| weather.text(units)),
+            "aqi" => readings.get::<AqiSource>().map(|aqi| aqi.text()),
+            "uv" => readings.get::<UvSource>().and_then(|uv| uv.text()),
+            "mail" => readings.get::<EmailSource>().map(|mail| mail.text()),
+            "run_today" => readings
+                .get::<StravaSource>()
+                .map(|runs| runs.today_text(units)),
+            "run_week" => readings
+                .get::<StravaSource>()
+                .map(|runs| runs.week_text(units)),
+            _ => bail!("unknown text source `{}`", source),
+        })
+    }
+
+    impl Layout {
+        pub fn parse(json: &str) -> Result<Layout> {
+            let value: Value = serde_json::from_str(json).context("Could not parse layout")?;
+            Layout::from_value(value, "layout")
+        }
+
+        /// A layout nested in another file, with errors reported
+        /// relative to `path`.
+        pub(crate) fn from_value(value: Value, path: &str) -> Result<Layout> {
+            let root = deserialize_node(value, path)?;
+            root.validate(path)?;
+            Ok(Layout { root })
+        }
+
+        pub fn load(path: &Path) -> Result<Layout> {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Could not read layout {}", path.display()))?;
+            Layout::parse(&json).with_context(|| format!("Invalid layout {}", path.display()))
+        }
+
+        /// Build the layout, or `None` if nothing in it has anything to show.
+        pub(crate) fn build(&self, data: &Data) -> Result<Option<Box<dyn Widget>>> {
+            self.root.build(data)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::playlist::playlist::Playlist;
+        use chrono::TimeZone;
+
+        fn error(json: &str) -> String {
+            Layout::parse(json).unwrap_err().to_string()
+        }
+
+        #[test]
+        fn unknown_node_types_name_their_path() {
+            let err =
+                error(r#"{"type": "vstack", "items": [{"type": "clock"}, {"type": "banner"}]}"#);
+            assert!(
+                err.starts_with("layout.items[1] (banner): unknown variant `banner`"),
+                "{}",
+                err
+            );
+        }
+
+        #[test]
+        fn nested_bad_fields_name_their_path() {
+            let json = |text: &str| {
+                format!(
+                    r#"{{"type": "vstack", "items": [
+                        {{"type": "clock"}},
+                        {{"type": "date"}},
+                        {{"type": "hstack", "items": [{}]}}
+                    ]}}"#,
+                    text
+                )
+            };
+            let err = error(&json(r#"{"type": "text", "text": "hi", "colour": "red"}"#));
+            assert!(
+                err.starts_with("layout.items[2].items[0] (text): unknown field `colour`"),
+                "{}",
+                err
+            );
+            let err = error(&json(r#"{"type": "text", "text": "hi", "source": "mail"}"#));
+            assert_eq!(
+                err,
+                "layout.items[2].items[0] (text): has both `text` and `source`"
+            );
+        }
+
+        #[test]
+        fn bad_stale_afters_name_their_path() {
+            let err =
+                error(r#"{"type": "vstack", "items": [{"type": "weather", "stale_after": 0}]}"#);
+            assert_eq!(
+                err,
+                "layout.items[0] (weather): stale_after must be at least 1 second"
+            );
+            let err =
+                error(r#"{"type": "vstack", "items": [{"type": "weather", "stale_after": -5}]}"#);
+            assert!(err.starts_with("layout.items[0] (weather): "), "{}", err);
+        }
+
+        #[test]
+        fn the_bundled_layouts_build() {
+            let data =
+                Data::sample(Local.with_ymd_and_hms(2026, 10, 18, 9, 30, 0).unwrap()).unwrap();
+            let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("layouts");
+            let mut count = 0;
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                    continue;
+                }
+                let json: Value =
+                    serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+                let widgets = if json.get("screens").is_some() {
+                    let playlist = Playlist::load(&path).unwrap();
+                    playlist
+                        .screens
+                        .iter()
+                        .map(|screen| screen.build(&data))
+                        .collect::<Result<Vec<_>>>()
+                        .unwrap()
+                } else {
+                    vec![Layout::load(&path).unwrap().build(&data).unwrap()]
+                };
+                for widget in widgets {
+                    let widget =
+                        widget.unwrap_or_else(|| panic!("{} built nothing", path.display()));
+                    crate::render_frames(widget.as_ref(), 64, 32).unwrap();
+                }
+                count += 1;
+            }
+            assert!(count >= 2);
+        }
+    }
+}
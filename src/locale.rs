@@ -0,0 +1,158 @@
+/// How values are written out: units, and time and date formats.
+pub mod locale {
+    use serde::Deserialize;
+    use uom::si::f64::{Length, ThermodynamicTemperature};
+    use uom::si::length::{kilometer, meter, mile};
+    use uom::si::thermodynamic_temperature::{degree_celsius, degree_fahrenheit};
+
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Units {
+        /// Miles and Fahrenheit.
+        #[default]
+        Imperial,
+        /// Kilometers and Celsius.
+        Metric,
+    }
+
+    impl Units {
+        /// A temperature from the NWS, which is in Fahrenheit.
+        pub fn temperature(&self, fahrenheit: i32) -> i32 {
+            let temperature = ThermodynamicTemperature::new::<degree_fahrenheit>(fahrenheit as f64);
+            match self {
+                Units::Imperial => fahrenheit,
+                Units::Metric => temperature.get::<degree_celsius>().round() as i32,
+            }
+        }
+
+        /// A distance from Strava, which is in meters.
+        pub fn distance(&self, meters: f64) -> f64 {
+            let length = Length::new::<meter>(meters);
+            match self {
+                Units::Imperial => length.get::<mile>(),
+                Units::Metric => length.get::<kilometer>(),
+            }
+        }
+
+        pub fn distance_suffix(&self) -> &'static str {
+            match self {
+                Units::Imperial => "MI",
+                Units::Metric => "KM",
+            }
+        }
+    }
+
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+    pub enum ClockStyle {
+        #[default]
+        #[serde(rename = "12h")]
+        TwelveHour,
+        #[serde(rename = "24h")]
+        TwentyFourHour,
+    }
+
+    fn default_date_format() -> String {
+        String::from("%b %e")
+    }
+
+    /// The `locale` section of the config.
+    #[derive(Clone, Debug, Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    pub struct Locale {
+        pub units: Units,
+        pub clock: ClockStyle,
+        /// A strftime format for `date` nodes that don't give their own.
+        pub date: String,
+    }
+
+    impl Default for Locale {
+        fn default() -> Locale {
+            Locale {
+                units: Units::default(),
+                clock: ClockStyle::default(),
+                date: default_date_format(),
+            }
+        }
+    }
+
+    impl Locale {
+        /// The strftime format for `clock` nodes that don't give their own.
+        pub fn time_format(&self) -> &'static str {
+            match self.clock {
+                ClockStyle::TwelveHour => "%l:%M",
+                ClockStyle::TwentyFourHour => "%H:%M",
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::NaiveDate;
+
+        #[test]
+        fn temperatures_convert_to_celsius() {
+            for (fahrenheit, celsius) in [
+                (32, 0),
+                (212, 100),
+                (-40, -40),
+                (72, 22),
+                (98, 37),
+                (0, -18),
+                (33, 1),
+                (31, -1),
+            ] {
+                assert_eq!(
+                    Units::Metric.temperature(fahrenheit),
+                    celsius,
+                    "{}°F",
+                    fahrenheit
+                );
+                assert_eq!(Units::Imperial.temperature(fahrenheit), fahrenheit);
+            }
+        }
+
+        #[test]
+        fn distances_convert_from_meters() {
+            let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+            assert!(close(Units::Imperial.distance(1609.344), 1.0));
+            assert!(close(Units::Metric.distance(1609.344), 1.609344));
+            assert!(close(Units::Metric.distance(5000.0), 5.0));
+            assert!(close(Units::Imperial.distance(42195.0), 26.218757456454306));
+            assert_eq!(Units::Imperial.distance_suffix(), "MI");
+            assert_eq!(Units::Metric.distance_suffix(), "KM");
+        }
+
+        #[test]
+        fn clocks_are_12_or_24_hour() {
+            let day = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+            let twelve = Locale::default();
+            let twenty_four = Locale {
+                clock: ClockStyle::TwentyFourHour,
+                ..Locale::default()
+            };
+            for (hour, minute, twelve_hour, twenty_four_hour) in [
+                (0, 5, "12:05", "00:05"),
+                (9, 30, " 9:30", "09:30"),
+                (12, 0, "12:00", "12:00"),
+                (13, 5, " 1:05", "13:05"),
+                (23, 59, "11:59", "23:59"),
+            ] {
+                let time = day.and_hms_opt(hour, minute, 0).unwrap();
+                assert_eq!(time.format(twelve.time_format()).to_string(), twelve_hour);
+                assert_eq!(
+                    time.format(twenty_four.time_format()).to_string(),
+                    twenty_four_hour
+                );
+            }
+        }
+
+        #[test]
+        fn dates_default_to_month_and_day() {
+            let locale = Locale::default();
+            let date = |day| NaiveDate::from_ymd_opt(2026, 10, day).unwrap();
+            assert_eq!(date(18).format(&locale.date).to_string(), "Oct 18");
+            assert_eq!(date(5).format(&locale.date).to_string(), "Oct  5");
+        }
+    }
+}
@@ -0,0 +1,310 @@
+/// Where the display is, and the weather, air quality and UV
+/// stations that go with it.
+pub mod location {
+    use chrono_tz::Tz;
+    use once_cell::sync::OnceCell;
+    use reqwest::header::USER_AGENT;
+    use serde::{Deserialize, Serialize};
+    use std::path::{Path, PathBuf};
+    use thiserror::Error;
+    use tokio::sync::Mutex;
+
+    /// A place, and optionally the stations to use for it. Stations
+    /// that aren't given are looked up from the latitude and longitude.
+    #[derive(Debug, Clone, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Location {
+        pub lat: f64,
+        pub lon: f64,
+        /// For the EPA's UV forecast, which only goes by ZIP code.
+        pub zip: Option<String>,
+        /// An NWS forecast office and grid square, like `OKX/33,33`.
+        pub gridpoint: Option<String>,
+        /// An AirNow site ID, like `360470118`.
+        pub airnow_site: Option<String>,
+        /// The IANA time zone, like `America/New_York`, which the
+        /// UV forecast's hours are in.
+        pub timezone: Option<String>,
+    }
+
+    /// Brooklyn, which is where this started out hard-coded.
+    impl Default for Location {
+        fn default() -> Location {
+            Location {
+                lat: 40.692778,
+                lon: -73.990278,
+                zip: Some(String::from("11201")),
+                gridpoint: Some(String::from("OKX/33,33")),
+                airnow_site: Some(String::from("360470118")),
+                timezone: Some(String::from("America/New_York")),
+            }
+        }
+    }
+
+    static HOME: OnceCell<Location> = OnceCell::new();
+
+    /// Set where the display is, for things like sun darkening
+    /// that don't have a source to carry it.
+    pub fn set_home(location: Location) {
+        let _ = HOME.set(location);
+    }
+
+    pub fn home() -> &'static Location {
+        HOME.get_or_init(Location::default)
+    }
+
+    #[derive(Debug, Error)]
+    pub enum LocationError {
+        #[error("could not look up the location: {0}")]
+        Request(#[from] reqwest::Error),
+        #[error("there's no active AirNow site near {0}, {1}")]
+        NoSite(f64, f64),
+        #[error("unknown time zone `{0}`")]
+        TimeZone(String),
+    }
+
+    /// Parse an IANA time zone name.
+    pub fn parse_timezone(name: &str) -> Result<Tz, LocationError> {
+        name.parse()
+            .map_err(|_| LocationError::TimeZone(String::from(name)))
+    }
+
+    /// Stations looked up for a location, saved so that
+    /// they're only looked up again when it changes.
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct Resolved {
+        lat: f64,
+        lon: f64,
+        gridpoint: Option<String>,
+        airnow_site: Option<String>,
+        timezone: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct PointProperties {
+        #[serde(rename = "gridId")]
+        grid_id: String,
+        #[serde(rename = "gridX")]
+        grid_x: i32,
+        #[serde(rename = "gridY")]
+        grid_y: i32,
+        #[serde(rename = "timeZone")]
+        time_zone: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Point {
+        properties: PointProperties,
+    }
+
+    /// AirNow's list of monitoring sites, one pipe-separated line per
+    /// monitor. These are the columns we need from it.
+    const SITE_LIST: &str =
+        "https://files.airnowtech.org/airnow/today/Monitoring_Site_Locations_V2.dat";
+    const SITE_ID_COLUMN: usize = 1;
+    const SITE_STATUS_COLUMN: usize = 7;
+    const SITE_LAT_COLUMN: usize = 11;
+    const SITE_LON_COLUMN: usize = 12;
+
+    /// Great-circle distance in km.
+    fn distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+        let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (lon2 - lon1).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        6371.0 * 2.0 * a.sqrt().asin()
+    }
+
+    /// The closest active site in AirNow's site list.
+    fn nearest_site(list: &str, lat: f64, lon: f64) -> Option<String> {
+        list.lines()
+            .filter_map(|line| {
+                let columns: Vec<&str> = line.split('|').collect();
+                if columns.get(SITE_STATUS_COLUMN) != Some(&"Active") {
+                    return None;
+                }
+                let site_lat: f64 = columns.get(SITE_LAT_COLUMN)?.parse().ok()?;
+                let site_lon: f64 = columns.get(SITE_LON_COLUMN)?.parse().ok()?;
+                let id = columns.get(SITE_ID_COLUMN)?;
+                Some((distance(lat, lon, site_lat, site_lon), id.to_string()))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, id)| id)
+    }
+
+    /// Looks up the stations for a location the first time
+    /// they're needed, and remembers them in the state directory.
+    pub struct Resolver {
+        location: Location,
+        path: PathBuf,
+        resolved: Mutex<Resolved>,
+    }
+
+    impl Resolver {
+        pub fn new(location: Location, state_dir: &Path) -> Resolver {
+            let path = state_dir.join("location.json");
+            let resolved = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|json| serde_json::from_str::<Resolved>(&json).ok())
+                .filter(|resolved| resolved.lat == location.lat && resolved.lon == location.lon)
+                .unwrap_or(Resolved {
+                    lat: location.lat,
+                    lon: location.lon,
+                    ..Resolved::default()
+                });
+            Resolver {
+                location,
+                path,
+                resolved: Mutex::new(resolved),
+            }
+        }
+
+        pub fn location(&self) -> &Location {
+            &self.location
+        }
+
+        fn save(&self, resolved: &Resolved) {
+            let saved = serde_json::to_vec(resolved)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| Ok(std::fs::write(&self.path, json)?));
+            if let Err(err) = saved {
+                println!("Could not save {}: {:#}", self.path.display(), err);
+            }
+        }
+
+        /// Look up the gridpoint and time zone
+        /// from `/points/{lat},{lon}`.
+        async fn look_up_point(
+            &self,
+            resolved: &mut Resolved,
+        ) -> Result<(String, String), LocationError> {
+            println!("Looking up the NWS gridpoint");
+            let point: Point = reqwest::Client::new()
+                .get(format!(
+                    "https://api.weather.gov/points/{:.4},{:.4}",
+                    self.location.lat, self.location.lon
+                ))
+                .header(USER_AGENT, "tidbyt")
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            let PointProperties {
+                grid_id,
+                grid_x,
+                grid_y,
+                time_zone,
+            } = point.properties;
+            let gridpoint = format!("{}/{},{}", grid_id, grid_x, grid_y);
+            resolved.gridpoint = Some(gridpoint.clone());
+            resolved.timezone = Some(time_zone.clone());
+            self.save(resolved);
+            Ok((gridpoint, time_zone))
+        }
+
+        /// The NWS gridpoint.
+        pub async fn gridpoint(&self) -> Result<String, LocationError> {
+            if let Some(gridpoint) = &self.location.gridpoint {
+                return Ok(gridpoint.clone());
+            }
+            let mut resolved = self.resolved.lock().await;
+            if let Some(gridpoint) = &resolved.gridpoint {
+                return Ok(gridpoint.clone());
+            }
+            Ok(self.look_up_point(&mut resolved).await?.0)
+        }
+
+        /// The location's time zone, which NWS knows along
+        /// with the gridpoint.
+        pub async fn timezone(&self) -> Result<Tz, LocationError> {
+            if let Some(timezone) = &self.location.timezone {
+                return parse_timezone(timezone);
+            }
+            let mut resolved = self.resolved.lock().await;
+            if let Some(timezone) = &resolved.timezone {
+                return parse_timezone(timezone);
+            }
+            parse_timezone(&self.look_up_point(&mut resolved).await?.1)
+        }
+
+        /// The nearest active AirNow site.
+        pub async fn airnow_site(&self) -> Result<String, LocationError> {
+            if let Some(site) = &self.location.airnow_site {
+                return Ok(site.clone());
+            }
+            let mut resolved = self.resolved.lock().await;
+            if let Some(site) = &resolved.airnow_site {
+                return Ok(site.clone());
+            }
+            println!("Looking up the nearest AirNow site");
+            let list = reqwest::Client::new()
+                .get(SITE_LIST)
+                .header(USER_AGENT, "tidbyt")
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            let (lat, lon) = (self.location.lat, self.location.lon);
+            let site = nearest_site(&list, lat, lon).ok_or(LocationError::NoSite(lat, lon))?;
+            resolved.airnow_site = Some(site.clone());
+            self.save(&resolved);
+            Ok(site)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // Lines in the layout of AirNow's Monitoring_Site_Locations_V2.dat.
+        const PS_274: &str = "360470118|360470118|840360470118|PM2.5|Permanent|0118|PS 274|Active|NY1|New York State Department of Environmental Conservation|R2|40.694540|-73.927690|25.0|-5.00|US|35620|New York-Newark-Jersey City, NY-NJ-PA|36|NY|047|KINGS";
+        const CCNY: &str = "360610135|360610135|840360610135|OZONE|Permanent|0135|CCNY|Active|NY1|New York State Department of Environmental Conservation|R2|40.819760|-73.948250|35.0|-5.00|US|35620|New York-Newark-Jersey City, NY-NJ-PA|36|NY|061|NEW YORK";
+        const INACTIVE: &str = "360470052|360470052|840360470052|PM2.5|Permanent|0052|PS 321|Inactive|NY1|New York State Department of Environmental Conservation|R2|40.692800|-73.990300|20.0|-5.00|US|35620|New York-Newark-Jersey City, NY-NJ-PA|36|NY|047|KINGS";
+
+        const BROOKLYN: (f64, f64) = (40.692778, -73.990278);
+
+        #[test]
+        fn distances_are_along_the_earth() {
+            // A degree of longitude at the equator.
+            assert!((distance(0.0, 0.0, 0.0, 1.0) - 111.19).abs() < 0.01);
+            let (lat, lon) = BROOKLYN;
+            let there = distance(lat, lon, 40.819760, -73.948250);
+            let back = distance(40.819760, -73.948250, lat, lon);
+            assert!((there - 14.5).abs() < 0.1, "{}", there);
+            assert_eq!(there, back);
+            assert_eq!(distance(lat, lon, lat, lon), 0.0);
+        }
+
+        #[test]
+        fn the_nearest_active_site_wins() {
+            let (lat, lon) = BROOKLYN;
+            let list = [CCNY, PS_274].join("\n");
+            assert_eq!(nearest_site(&list, lat, lon).as_deref(), Some("360470118"));
+            let list = [PS_274, CCNY].join("\n");
+            assert_eq!(nearest_site(&list, lat, lon).as_deref(), Some("360470118"));
+            // From up in Harlem, CCNY is closer.
+            assert_eq!(
+                nearest_site(&list, 40.81, -73.95).as_deref(),
+                Some("360610135")
+            );
+            let list = [INACTIVE, CCNY, PS_274].join("\n");
+            assert_eq!(nearest_site(&list, lat, lon).as_deref(), Some("360470118"));
+        }
+
+        #[test]
+        fn malformed_lines_are_skipped() {
+            let (lat, lon) = BROOKLYN;
+            let bad_lat = PS_274
+                .replace("360470118", "360470999")
+                .replace("40.694540", "N/A");
+            let truncated =
+                "360470888|360470888|840360470888|PM2.5|Permanent|0888|Atlantic Ave|Active";
+            let list = ["", &bad_lat, truncated, "not a site", CCNY, ""].join("\n");
+            assert_eq!(nearest_site(&list, lat, lon).as_deref(), Some("360610135"));
+            assert_eq!(nearest_site("", lat, lon), None);
+            assert_eq!(nearest_site(truncated, lat, lon), None);
+        }
+    }
+}
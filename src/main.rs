@@ -1,221 +1,818 @@
 pub mod adjusted_color;
+pub mod animation;
 pub mod aqi;
+pub mod brightness;
+pub mod cache;
+pub mod chart;
+pub mod color;
+pub mod config;
 pub mod draw_buffer;
-use anyhow::{anyhow, Context, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 pub mod email;
+pub mod font;
+pub mod icon;
 pub mod image;
+pub mod layout;
+pub mod locale;
+pub mod location;
+pub mod playlist;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::watch;
 use tokio::time::{sleep, Duration};
 pub mod pusher;
+pub mod serve;
+pub mod source;
 pub mod strava;
+pub mod terminal;
+pub mod theme;
+pub mod timeline;
 pub mod uv;
 pub mod weather;
-use crate::draw_buffer::draw_buffer::get_rgba;
+use crate::draw_buffer::draw_buffer::Canvas;
 use adjusted_color::adjusted_color::adjusted_color;
-use aqi::aqi::get_aqi;
+use animation::animation::{encode, fingerprint, repeat_for, Frame};
+use brightness::brightness::{quantize, BrightnessPolicy};
+use cache::cache::{default_state_dir, DiskCache};
+use chart::chart::Chart;
 use chrono::prelude::*;
 use clap::Parser;
+use color::color::Calibration;
+use config::config::{Config, DeviceConfig, Shows};
 use dotenv::dotenv;
-use email::email::get_email_count;
-use pusher::pusher::push;
+use font::font::{font, FontName, TextAlign};
+use futures::future::join_all;
+use icon::icon::Icon;
+use layout::layout::{Data, Layout};
+use locale::locale::{Locale, Units};
+use location::location::{home, set_home, Resolver};
+use once_cell::sync::OnceCell;
+use playlist::playlist::{Mode, Playlist, DEFAULT_PLAYLIST};
+use pusher::pusher::{TidbytClient, DEFAULT_INSTALLATION};
 use raqote::*;
-use strava::strava::get_strava;
-use uv::uv::get_uv;
-use weather::weather::get_weather;
-use webp::{AnimEncoder, AnimFrame, WebPConfig};
+use serve::serve::{serve, Preview};
+use source::source::{default_registry, Readings};
+use terminal::terminal::TerminalPreview;
+use theme::theme::set_theme;
+use timeline::timeline::{Schedule, Timeline, DEFAULT_FRAME_DURATION};
+use weather::weather::Weather;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Filename of the debug file
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "serve")]
     debug: Option<String>,
+
+    /// Keep rendering and serve a preview on this address, like
+    /// 127.0.0.1:8080, instead of pushing
+    #[arg(long)]
+    serve: Option<std::net::SocketAddr>,
+
+    /// Keep rendering and play it in the terminal, instead of pushing
+    #[arg(long, conflicts_with_all = ["debug", "serve"])]
+    preview_terminal: bool,
+
+    /// Path to a JSON layout file, shown on its own instead of the built-in playlist
+    #[arg(short, long, conflicts_with = "playlist")]
+    layout: Option<std::path::PathBuf>,
+
+    /// Path to a JSON playlist file, instead of the built-in playlist
+    #[arg(short, long)]
+    playlist: Option<std::path::PathBuf>,
+
+    /// Render the layout this many times with sample data and report the time per frame
+    #[arg(long)]
+    bench: Option<u32>,
+
+    /// Path to a JSON config file, instead of ~/.tidbyt.json
+    #[arg(short, long)]
+    config: Option<std::path::PathBuf>,
+
+    /// Directory to cache source readings in, so restarts can show them straight away
+    #[arg(long)]
+    state_dir: Option<std::path::PathBuf>,
+
+    /// How many times the animation plays, where 0 loops forever
+    #[arg(long, default_value_t = 0)]
+    loop_count: i32,
+
+    /// The installation on the Tidbyt to push to, letters and numbers only
+    #[arg(long, default_value = DEFAULT_INSTALLATION)]
+    installation: String,
+
+    /// Switch the Tidbyt to the installation on each push, instead of
+    /// updating it in the background
+    #[arg(long)]
+    foreground: bool,
+
+    /// List what's installed on the Tidbyt and exit
+    #[arg(long)]
+    list_installations: bool,
+
+    /// Delete an installation from the Tidbyt and exit
+    #[arg(long)]
+    delete_installation: Option<String>,
+
+    /// The configured device to list or delete installations on,
+    /// needed when there's more than one, or to preview in the terminal
+    #[arg(long)]
+    device: Option<String>,
+
+    /// Show everything at this brightness, from 0 to 1, instead of
+    /// following the sun and quiet hours
+    #[arg(long)]
+    brightness: Option<f64>,
+
+    /// Push an unchanged image again once this many seconds have passed
+    /// since the last push, in case the Tidbyt missed it
+    #[arg(long, default_value_t = 900)]
+    force_push_after: u64,
 }
 
 // Built in 2px of buffer.
 const WIDTH: i64 = 61;
 
-#[derive(Copy, Clone)]
-enum TextAlign {
-    Left,
-    Right,
-}
-
-fn draw_text(
-    dt: &mut DrawTarget,
-    text: &str,
-    in_start: Point,
-    color: &Source,
-    align: TextAlign,
-) -> Result<()> {
-    let tom_thumb = include_bytes!("../fonts/tb-8.bdf");
-    let font: bdf::Font = bdf::read(&tom_thumb[..])?;
-    let mut start = in_start.clone();
-    let chars: Vec<char> = match align {
-        TextAlign::Left => text.chars().collect(),
-        TextAlign::Right => text.chars().rev().collect(),
+// The Tidbyt's display, in pixels.
+const DISPLAY_WIDTH: i32 = 64;
+const DISPLAY_HEIGHT: i32 = 32;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv().ok();
+    let args = Args::parse();
+    let ten_seconds = Duration::from_secs(10);
+    let config = Config::find(args.config.as_deref())?;
+    if let Some(level) = args.brightness {
+        if !(0.0..=1.0).contains(&level) {
+            bail!("--brightness must be between 0 and 1, not {}", level);
+        }
+    }
+    set_home(config.location.clone());
+    set_theme(config.theme.theme()?);
+
+    if let Some(iterations) = args.bench {
+        return bench(&load_playlist(&args, &Shows::Default)?, iterations);
+    }
+
+    if args.list_installations {
+        for installation in managed_client(&args, &config)?.installations().await? {
+            println!("{}\t{}", installation.id, installation.app_id);
+        }
+        return Ok(());
+    }
+    if let Some(id) = &args.delete_installation {
+        managed_client(&args, &config)?
+            .delete_installation(id)
+            .await?;
+        println!("Deleted {}", id);
+        return Ok(());
+    }
+
+    let preview = args
+        .serve
+        .map(|_| Preview::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32));
+    let terminal = args
+        .preview_terminal
+        .then(|| TerminalPreview::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32));
+    let groups = groups(&args, &config, preview.as_ref(), terminal)?;
+
+    let state_dir = match &args.state_dir {
+        Some(dir) => dir.clone(),
+        None => default_state_dir()?,
     };
-    for c in chars {
-        let glyph = font.glyphs().get(&c).context("Could not get glyph")?;
-
-        // The tom-thumb font is monospace but some of the characters
-        // don't take up the full bounding box. Offset them so that
-        // they sit at the center of their bits.
-
-        for px in glyph.pixels() {
-            let x = px.0 .0;
-            let y = px.0 .1;
-            let white = px.1;
-            if white {
-                dt.fill_rect(
-                    start.x + x as f32 + 0 as f32,
-                    start.y + y as f32 + 0 as f32,
-                    1.,
-                    1.,
-                    color,
-                    &DrawOptions::new(),
-                )
+    let cache = DiskCache::new(&state_dir)?;
+    let resolver = Arc::new(Resolver::new(config.location.clone(), &state_dir));
+    let mut registry = default_registry(&resolver, &config).set_cache(cache);
+    // With everything cached, show that straight away and refresh in
+    // the background. Otherwise wait for a first fetch, rather than
+    // pushing an empty screen.
+    if args.debug.is_some() || !registry.warm() {
+        registry.refresh().await;
+    }
+    let readings = registry.subscribe();
+    if args.debug.is_none() {
+        tokio::spawn(registry.run());
+    }
+    if let (Some(addr), Some(preview)) = (args.serve, &preview) {
+        let server = serve(addr, preview.clone(), readings.clone());
+        tokio::spawn(async move {
+            if let Err(err) = server.await {
+                println!("Could not serve the preview: {:#}", err);
             }
-        }
-        start.x = start.x
-            + advance(c)
-                * match align {
-                    TextAlign::Left => 1.0,
-                    TextAlign::Right => -1.0,
-                }
+        });
     }
+
+    // Each group keeps its own pace, since playlists run for different lengths.
+    let runs = groups.into_iter().map(|mut group| {
+        let (args, locale, readings) = (&args, &config.locale, readings.clone());
+        async move {
+            loop {
+                let wait = match render(args, &mut group, locale, &readings).await {
+                    Ok(wait) => wait,
+                    Err(err) => {
+                        println!("{:?}", err);
+                        ten_seconds
+                    }
+                };
+
+                if args.debug.is_some() {
+                    break;
+                }
+
+                sleep(wait).await;
+            }
+        }
+    });
+    join_all(runs).await;
     Ok(())
 }
 
-fn advance(c: char) -> f32 {
-    if c == ' ' {
-        return 2.0;
+// What to show for a device, from its config or the command line.
+fn load_playlist(args: &Args, shows: &Shows) -> Result<Playlist> {
+    match shows {
+        Shows::Layout(path) => Ok(Playlist::single(Layout::load(path)?)),
+        Shows::Playlist(path) => Playlist::load(path),
+        Shows::Default => match (&args.layout, &args.playlist) {
+            (Some(path), _) => Ok(Playlist::single(Layout::load(path)?)),
+            (None, Some(path)) => Playlist::load(path),
+            (None, None) => Playlist::parse(DEFAULT_PLAYLIST),
+        },
     }
-    let tom_thumb = include_bytes!("../fonts/tb-8.bdf");
-    let font = bdf::read(&tom_thumb[..]);
-    let w = font.map(|f| f.glyphs().get(&c).map(|g| g.width()));
-    w.unwrap_or(Some(0)).unwrap_or(0) as f32
 }
 
-#[tokio::main]
-async fn main() {
-    dotenv().ok();
-    let args = Args::parse();
-    let ten_seconds = Duration::from_secs(10);
-
-    loop {
-        render(&args).await;
+// The client for --list-installations and --delete-installation.
+fn managed_client(args: &Args, config: &Config) -> Result<TidbytClient> {
+    let device = match (&args.device, config.devices.as_slice()) {
+        (Some(name), devices) => devices
+            .iter()
+            .find(|device| &device.name == name)
+            .ok_or_else(|| anyhow!("No device named `{}` in the config", name))?,
+        (None, []) => return Ok(TidbytClient::from_env()?),
+        (None, [device]) => device,
+        (None, _) => bail!("There's more than one device in the config, pick one with --device"),
+    };
+    Ok(TidbytClient::new(device.id.clone(), device.key.clone()))
+}
 
-        if args.debug.is_some() {
-            break;
+// The devices to push to, grouped by what they show so that
+// each playlist is rendered once. Without any devices in the
+// config, there's one from the environment and command line.
+// The terminal only has room for one, picked with --device.
+fn groups(
+    args: &Args,
+    config: &Config,
+    preview: Option<&Preview>,
+    mut terminal: Option<TerminalPreview>,
+) -> Result<Vec<Group>> {
+    // Debug output and previews don't need credentials.
+    let local = args.debug.is_some() || preview.is_some() || terminal.is_some();
+    let client = |device: Option<&DeviceConfig>| -> Result<Option<TidbytClient>> {
+        Ok(match (local, device) {
+            (true, _) => None,
+            (false, Some(device)) => Some(TidbytClient::new(device.id.clone(), device.key.clone())),
+            (false, None) => Some(TidbytClient::from_env()?),
+        })
+    };
+    if config.devices.is_empty() {
+        return Ok(vec![Group {
+            playlist: load_playlist(args, &Shows::Default)?,
+            devices: vec![Device {
+                name: None,
+                client: client(None)?,
+                installation: args.installation.clone(),
+                background: !args.foreground,
+                brightness: config.brightness.clone(),
+                calibration: config.calibration.clone(),
+                preview: preview.cloned(),
+                terminal,
+                last_push: None,
+            }],
+        }]);
+    }
+    let configured: Vec<&DeviceConfig> = match (&terminal, &args.device) {
+        (Some(_), Some(name)) => vec![config
+            .devices
+            .iter()
+            .find(|device| &device.name == name)
+            .ok_or_else(|| anyhow!("No device named `{}` in the config", name))?],
+        (Some(_), None) => config.devices.iter().take(1).collect(),
+        (None, _) => config.devices.iter().collect(),
+    };
+    let mut groups: Vec<(Shows, Group)> = Vec::new();
+    for device_config in configured {
+        let device = Device {
+            name: Some(device_config.name.clone()),
+            client: client(Some(device_config))?,
+            installation: device_config.installation.clone(),
+            background: !device_config.foreground,
+            brightness: device_config.brightness(config).clone(),
+            calibration: device_config.calibration(config).clone(),
+            preview: preview.cloned(),
+            terminal: terminal.take(),
+            last_push: None,
+        };
+        let shows = device_config.shows();
+        match groups.iter_mut().find(|(other, _)| *other == shows) {
+            Some((_, group)) => group.devices.push(device),
+            None => {
+                let playlist = load_playlist(args, &shows).with_context(|| {
+                    format!("Could not load what `{}` shows", device_config.name)
+                })?;
+                groups.push((
+                    shows,
+                    Group {
+                        playlist,
+                        devices: vec![device],
+                    },
+                ));
+            }
         }
-
-        sleep(ten_seconds).await;
     }
+    Ok(groups.into_iter().map(|(_, group)| group).collect())
 }
 
 trait Widget: Send {
     // Gets the width of the given widget
     fn measure(&self) -> Point;
+    // Distance from the top of the widget to its text baseline,
+    // if it has one, so that stacks can line up mixed fonts.
+    fn baseline(&self) -> Option<f32> {
+        None
+    }
     fn frame_count(&self) -> u32;
-    fn render(&self, dt: &mut DrawTarget, point: Point, frame: u32) -> Result<(), Error>;
+    // How long the given frame shows for, in milliseconds.
+    fn frame_duration(&self, _frame: u32) -> u32 {
+        DEFAULT_FRAME_DURATION
+    }
+    fn render(&self, dt: &mut Canvas, point: Point, frame: u32) -> Result<(), Error>;
 }
 
 #[derive(Clone)]
 pub struct TextWidget {
     text: String,
     color: String,
+    font: FontName,
+    // Drawn dimmed, because the value is out of date
+    stale: bool,
 }
 
+// How much light widgets showing stale data keep.
+const STALE_LIGHT: f32 = 0.4;
+
 impl TextWidget {
     fn new(text: String, color: String) -> Result<TextWidget, anyhow::Error> {
-        Ok::<TextWidget, anyhow::Error>(TextWidget { text, color })
+        Ok::<TextWidget, anyhow::Error>(TextWidget {
+            text,
+            color,
+            font: FontName::default(),
+            stale: false,
+        })
+    }
+    fn set_font(mut self, font: FontName) -> TextWidget {
+        self.font = font;
+        self
+    }
+    fn set_stale(mut self, stale: bool) -> TextWidget {
+        self.stale = stale;
+        self
     }
 }
 
 impl Widget for TextWidget {
     fn measure(&self) -> Point {
-        let width: f32 = self.text.chars().map(|x| advance(x)).sum();
-        Point::new(width, 8.0)
+        match font(self.font) {
+            Ok(f) => Point::new(f.width(&self.text), f.height()),
+            Err(_) => Point::new(0.0, 0.0),
+        }
+    }
+    fn baseline(&self) -> Option<f32> {
+        font(self.font).ok().map(|f| f.ascent)
     }
     fn frame_count(&self) -> u32 {
         1
     }
-    fn render(&self, dt: &mut DrawTarget, point: Point, frame: u32) -> Result<(), Error> {
-        let color = adjusted_color(&self.color)?;
-        draw_text(dt, &self.text, point, &color, TextAlign::Left)
+    fn render(&self, dt: &mut Canvas, point: Point, frame: u32) -> Result<(), Error> {
+        font(self.font)?.draw(
+            dt,
+            &self.text,
+            point,
+            &adjusted_color(&self.color)?,
+            TextAlign::Left,
+        )?;
+        if self.stale {
+            dt.dim(point, self.measure(), STALE_LIGHT);
+        }
+        Ok(())
     }
 }
 
-struct ChartWidget {
-    data: Vec<u64>,
-    height: i32,
+/**
+ * Text that scrolls horizontally inside a clipped box
+ * when it's wider than the box.
+ */
+struct MarqueeWidget {
+    text: TextWidget,
+    width: f32,
+    // Pixels to scroll per frame, and how long each frame shows in ms
+    speed: u32,
+    frame_duration: u32,
+    // Extra ms to hold at the start, and once the end is visible
+    pause_start: u32,
+    pause_end: u32,
+    // Keep scrolling after the end so the text loops around,
+    // instead of jumping back to the start.
+    wrap: bool,
+    gap: f32,
+    steps: OnceCell<Vec<(u32, u32)>>,
 }
 
-impl ChartWidget {
-    fn new(data: &Vec<u64>) -> Result<ChartWidget, anyhow::Error> {
-        Ok::<ChartWidget, anyhow::Error>(ChartWidget {
-            data: data.clone(),
-            height: 5,
+impl MarqueeWidget {
+    fn new(text: TextWidget, width: f32) -> Result<MarqueeWidget, anyhow::Error> {
+        Ok::<MarqueeWidget, anyhow::Error>(MarqueeWidget {
+            text,
+            width,
+            speed: 1,
+            frame_duration: DEFAULT_FRAME_DURATION,
+            pause_start: 1000,
+            pause_end: 1000,
+            wrap: false,
+            gap: 8.0,
+            steps: OnceCell::new(),
         })
     }
+    fn set_speed(mut self, speed: u32, frame_duration: u32) -> MarqueeWidget {
+        self.speed = speed.max(1);
+        self.frame_duration = frame_duration.max(1);
+        self.steps = OnceCell::new();
+        self
+    }
+    fn set_pauses(mut self, pause_start: u32, pause_end: u32) -> MarqueeWidget {
+        self.pause_start = pause_start;
+        self.pause_end = pause_end;
+        self.steps = OnceCell::new();
+        self
+    }
+    fn set_wrap(mut self, wrap: bool, gap: f32) -> MarqueeWidget {
+        self.wrap = wrap;
+        self.gap = gap;
+        self.steps = OnceCell::new();
+        self
+    }
+
+    // How far the text has to move before its end is visible.
+    fn overflow(&self) -> u32 {
+        (self.text.measure().x - self.width).max(0.0).ceil() as u32
+    }
+
+    // The distance the text moves in one full loop.
+    fn period(&self) -> u32 {
+        (self.text.measure().x + self.gap).ceil() as u32
+    }
+
+    // Each frame's offset and duration, worked out once.
+    fn steps(&self) -> &[(u32, u32)] {
+        self.steps.get_or_init(|| self.scroll())
+    }
+
+    // The pauses are single long frames, and with `wrap` the text
+    // scrolls on past the end until the start comes back around.
+    fn scroll(&self) -> Vec<(u32, u32)> {
+        let overflow = self.overflow();
+        if overflow == 0 {
+            return vec![(0, DEFAULT_FRAME_DURATION)];
+        }
+        let mut steps = vec![(0, self.frame_duration + self.pause_start)];
+        let mut offset = 0;
+        while offset < overflow {
+            offset = (offset + self.speed).min(overflow);
+            steps.push((offset, self.frame_duration));
+        }
+        if let Some(last) = steps.last_mut() {
+            last.1 += self.pause_end;
+        }
+        if self.wrap {
+            let period = self.period();
+            offset += self.speed;
+            while offset < period {
+                steps.push((offset, self.frame_duration));
+                offset += self.speed;
+            }
+        }
+        steps
+    }
+
+    fn offset(&self, frame: u32) -> f32 {
+        let steps = self.steps();
+        steps[frame as usize % steps.len()].0 as f32
+    }
+}
+
+impl Widget for MarqueeWidget {
+    fn measure(&self) -> Point {
+        Point::new(self.width, self.text.measure().y)
+    }
+    fn baseline(&self) -> Option<f32> {
+        self.text.baseline()
+    }
+    fn frame_count(&self) -> u32 {
+        self.steps().len() as u32
+    }
+    fn frame_duration(&self, frame: u32) -> u32 {
+        let steps = self.steps();
+        steps[frame as usize % steps.len()].1
+    }
+    fn render(&self, dt: &mut Canvas, point: Point, frame: u32) -> Result<()> {
+        // Text is blitted past raqote's clip stack, so clip by
+        // drawing into a target the size of the box.
+        let size = self.measure();
+        let (width, height) = (size.x.ceil() as i32, size.y.ceil() as i32);
+        let mut clipped = Canvas::new(width, height);
+        let x = -self.offset(frame);
+        self.text.render(&mut clipped, Point::new(x, 0.0), frame)?;
+        if self.wrap {
+            let next = Point::new(x + self.period() as f32, 0.0);
+            self.text.render(&mut clipped, next, frame)?;
+        }
+        dt.blend(&clipped, IntPoint::new(point.x as i32, point.y as i32));
+        Ok(())
+    }
+}
+
+/**
+ * A chart from the chart module, dimmed when its data is stale.
+ */
+pub struct ChartWidget {
+    chart: Chart,
+    stale: bool,
+}
+
+impl ChartWidget {
+    fn new(chart: Chart) -> ChartWidget {
+        ChartWidget {
+            chart,
+            stale: false,
+        }
+    }
+    fn set_stale(mut self, stale: bool) -> ChartWidget {
+        self.stale = stale;
+        self
+    }
 }
 
 impl Widget for ChartWidget {
     fn measure(&self) -> Point {
-        Point::new(self.data.len() as f32, 8.0)
+        Point::new(self.chart.width() as f32, self.chart.height() as f32)
     }
     fn frame_count(&self) -> u32 {
         1
     }
-    fn render(&self, dt: &mut DrawTarget, point: Point, frame: u32) -> Result<()> {
-        if self.data.is_empty() {
-            return Ok(());
+    fn render(&self, dt: &mut Canvas, point: Point, _frame: u32) -> Result<()> {
+        self.chart.render(dt, point)?;
+        if self.stale {
+            dt.dim(point, self.measure(), STALE_LIGHT);
+        }
+        Ok(())
+    }
+}
+
+/**
+ * The current conditions as an icon and the temperature, with
+ * the day's high over its low, and the chance of precipitation
+ * when it's likely in the next few hours.
+ */
+struct WeatherWidget {
+    weather: Weather,
+    units: Units,
+    stale: bool,
+}
+
+// Gaps between the parts of the weather widget.
+const WEATHER_GAP: f32 = 2.0;
+
+impl WeatherWidget {
+    fn new(weather: Weather, units: Units) -> WeatherWidget {
+        WeatherWidget {
+            weather,
+            units,
+            stale: false,
+        }
+    }
+    fn set_stale(mut self, stale: bool) -> WeatherWidget {
+        self.stale = stale;
+        self
+    }
+
+    fn temperature(&self) -> String {
+        format!("{}°", self.units.temperature(self.weather.temperature))
+    }
+
+    fn high_low(&self) -> (String, String) {
+        (
+            self.units.temperature(self.weather.high).to_string(),
+            self.units.temperature(self.weather.low).to_string(),
+        )
+    }
+
+    fn precipitation(&self) -> Option<String> {
+        self.weather
+            .precipitation_likely()
+            .then(|| format!("{}%", self.weather.precipitation))
+    }
+
+    // The high and low are stacked in two rows of the small font,
+    // which sets the height, and everything sits on the bottom row.
+    fn height(&self) -> Result<f32> {
+        Ok(font(FontName::CgPixel3x5)?.height() * 2.0 + 1.0)
+    }
+}
+
+impl Widget for WeatherWidget {
+    fn measure(&self) -> Point {
+        let (Ok(large), Ok(small), Ok(height)) = (
+            font(FontName::Tb8),
+            font(FontName::CgPixel3x5),
+            self.height(),
+        ) else {
+            return Point::new(0.0, 0.0);
+        };
+        let (high, low) = self.high_low();
+        let high_low = small.width(&high).max(small.width(&low));
+        let mut width = self.weather.icon().width()
+            + WEATHER_GAP
+            + large.width(&self.temperature())
+            + WEATHER_GAP
+            + high_low;
+        if let Some(precipitation) = self.precipitation() {
+            width += WEATHER_GAP + Icon::Drop.width() + 1.0 + small.width(&precipitation);
+        }
+        Point::new(width, height)
+    }
+    fn baseline(&self) -> Option<f32> {
+        self.height().ok()
+    }
+    fn frame_count(&self) -> u32 {
+        1
+    }
+    fn render(&self, dt: &mut Canvas, point: Point, _frame: u32) -> Result<()> {
+        let (large, small, height) = (
+            font(FontName::Tb8)?,
+            font(FontName::CgPixel3x5)?,
+            self.height()?,
+        );
+        let bottom_row = point.y + height - small.height();
+
+        let icon = self.weather.icon();
+        icon.draw(dt, Point::new(point.x, point.y + height - icon.height()))?;
+        let mut x = point.x + icon.width() + WEATHER_GAP;
+
+        let temperature = self.temperature();
+        let top = point.y + height - large.ascent;
+        large.draw(
+            dt,
+            &temperature,
+            Point::new(x, top),
+            &adjusted_color("text")?,
+            TextAlign::Left,
+        )?;
+        x += large.width(&temperature) + WEATHER_GAP;
+
+        let (high, low) = self.high_low();
+        small.draw(
+            dt,
+            &high,
+            Point::new(x, point.y),
+            &adjusted_color("warm")?,
+            TextAlign::Left,
+        )?;
+        small.draw(
+            dt,
+            &low,
+            Point::new(x, bottom_row),
+            &adjusted_color("cold")?,
+            TextAlign::Left,
+        )?;
+        x += small.width(&high).max(small.width(&low));
+
+        if let Some(precipitation) = self.precipitation() {
+            x += WEATHER_GAP;
+            Icon::Drop.draw(dt, Point::new(x, bottom_row))?;
+            x += Icon::Drop.width() + 1.0;
+            small.draw(
+                dt,
+                &precipitation,
+                Point::new(x, bottom_row),
+                &adjusted_color("precip")?,
+                TextAlign::Left,
+            )?;
+        }
+        if self.stale {
+            dt.dim(point, self.measure(), STALE_LIGHT);
         }
-        let mut pt = point.clone();
-        for d in &self.data {
-            let mut h = (d + 1) as f32;
-            let high = h > 8.0;
-            if high {
-                h = 8.0;
+        Ok(())
+    }
+}
+
+/**
+ * A tiny red cross, shown in place of a source
+ * that has never fetched successfully.
+ */
+struct ErrorWidget;
+
+impl Widget for ErrorWidget {
+    fn measure(&self) -> Point {
+        Point::new(3.0, 3.0)
+    }
+    // Sit on the baseline of any text beside it.
+    fn baseline(&self) -> Option<f32> {
+        Some(3.0)
+    }
+    fn frame_count(&self) -> u32 {
+        1
+    }
+    fn render(&self, dt: &mut Canvas, point: Point, _frame: u32) -> Result<()> {
+        let color = adjusted_color("bad")?;
+        for i in 0..3 {
+            for x in [i, 2 - i] {
+                dt.fill_rect(
+                    point.x + x as f32,
+                    point.y + i as f32,
+                    1.0,
+                    1.0,
+                    &color,
+                    &DrawOptions::new(),
+                );
             }
-            let color = adjusted_color(if high {
-                "#0ff"
-            } else if h > 1.0 {
-                "#eee"
-            } else {
-                "#555"
-            })?;
-            dt.fill_rect(
-                pt.x,
-                pt.y + (self.height as f32) - h,
-                1.0,
-                h,
-                &color,
-                &DrawOptions::new(),
-            );
-            pt.x += 1.0;
         }
         Ok(())
     }
 }
 
 /**
- * Horizontal stack
+ * Horizontal stack, with at least `gap` between items. When it
+ * expands, the items are spread out across the whole width.
  */
 struct HStack {
     items: Vec<Box<dyn Widget>>,
     gap: f32,
     expand: bool,
+    schedule: Schedule,
+    timeline: OnceCell<Timeline>,
+}
+
+// Each item's frame durations, for merging into a timeline.
+fn track(widget: &dyn Widget) -> Vec<u32> {
+    (0..widget.frame_count())
+        .map(|frame| widget.frame_duration(frame))
+        .collect()
+}
+
+fn items_timeline(items: &[Box<dyn Widget>], schedule: &Schedule) -> Timeline {
+    let tracks: Vec<Vec<u32>> = items.iter().map(|item| track(item.as_ref())).collect();
+    Timeline::new(&tracks, schedule)
 }
 
 impl HStack {
-    fn set_gap(mut self, gap: f32) -> HStack {
-        self.gap = gap;
-        self
+    fn timeline(&self) -> &Timeline {
+        self.timeline
+            .get_or_init(|| items_timeline(&self.items, &self.schedule))
     }
-    fn set_expand(mut self, expand: bool) -> HStack {
-        self.expand = expand;
-        self
+    // Text items are shifted down so that their baselines
+    // line up with the lowest baseline in the row.
+    fn line_baseline(&self) -> Option<f32> {
+        self.items
+            .iter()
+            .filter_map(|item| item.baseline())
+            .reduce(f32::max)
+    }
+    fn offset(&self, item: &dyn Widget) -> f32 {
+        match (self.line_baseline(), item.baseline()) {
+            (Some(line), Some(own)) => line - own,
+            _ => 0.0,
+        }
+    }
+    fn content_width(&self) -> f32 {
+        self.items.iter().map(|item| item.measure().x.round()).sum()
+    }
+    // The space after each item but the last.
+    fn spaces(&self) -> Vec<f32> {
+        let gap_count = self.items.len().saturating_sub(1);
+        let mut spaces = vec![self.gap; gap_count];
+        if !self.expand || gap_count == 0 {
+            return spaces;
+        }
+        let extra_room = WIDTH as f32 - self.content_width() - self.gap * gap_count as f32;
+        if extra_room > 0.0 {
+            let space_between = (extra_room / gap_count as f32).floor();
+            for space in spaces.iter_mut() {
+                *space += space_between;
+            }
+            // Add any extra remainder space to the last gap.
+            if let Some(last) = spaces.last_mut() {
+                *last += extra_room - space_between * gap_count as f32;
+            }
+        }
+        spaces
     }
 }
 
@@ -224,215 +821,409 @@ impl Widget for HStack {
         let max_height = self
             .items
             .iter()
-            .map(|item| item.measure().y.round() as u32)
+            .map(|item| (self.offset(item.as_ref()) + item.measure().y).round() as u32)
             .max()
-            .unwrap_or(5) as f32;
-        Point::new(WIDTH as f32, max_height)
+            .unwrap_or(0) as f32;
+        let width = self.content_width() + self.spaces().iter().sum::<f32>();
+        Point::new(width, max_height)
+    }
+    fn baseline(&self) -> Option<f32> {
+        self.line_baseline()
     }
     fn frame_count(&self) -> u32 {
-        self.items
-            .iter()
-            .map(|item| item.frame_count())
-            .max()
-            .unwrap_or(1)
+        self.timeline().frame_count()
     }
-    fn render(&self, dt: &mut DrawTarget, point: Point, frame: u32) -> Result<()> {
-        if self.items.is_empty() {
-        } else if self.items.len() == 1 {
-            if let Some(item) = self.items.first() {
-                item.render(dt, point, frame)?;
-            }
-        } else {
-            let widths: Vec<u32> = self
-                .items
-                .iter()
-                .map(|item| item.measure().x.round() as u32)
-                .collect();
-            let total_content_size = widths.iter().sum::<u32>() as i64;
-            let extra_room: i64 = WIDTH - total_content_size;
-            let gap_count = (self.items.len() - 1) as i64;
-            let mut spaces: Vec<f32> = Vec::new();
-            let space_between = extra_room / gap_count;
-            spaces.resize(self.items.len() - 1, space_between as f32);
-            let total_size_with_gaps = total_content_size + (gap_count * space_between);
-            let remainder = WIDTH - total_size_with_gaps;
-            // Add any extra remainder space to the last gap.
-            if remainder > 0 {
-                if let Some(last) = spaces.last_mut() {
-                    *last = *last + remainder as f32;
-                }
-            }
-            let mut start_point = point.clone();
-            for (i, item) in self.items.iter().enumerate() {
-                item.render(dt, start_point, frame)?;
-                start_point.x = start_point.x + item.measure().x + spaces.get(i).unwrap_or(&0.0);
-            }
+    fn frame_duration(&self, frame: u32) -> u32 {
+        self.timeline().duration(frame)
+    }
+    fn render(&self, dt: &mut Canvas, point: Point, frame: u32) -> Result<()> {
+        let timeline = self.timeline();
+        let spaces = self.spaces();
+        let mut start_point = point;
+        for (i, item) in self.items.iter().enumerate() {
+            let offset = self.offset(item.as_ref());
+            item.render(
+                dt,
+                Point::new(start_point.x, start_point.y + offset),
+                timeline.child_frame(frame, i),
+            )?;
+            start_point.x += item.measure().x.round() + spaces.get(i).unwrap_or(&0.0);
         }
         Ok(())
     }
 }
 
 /**
- * Horizontal stack
+ * Vertical stack, with `gap` between items.
  */
 struct VStack {
     items: Vec<Box<dyn Widget>>,
     gap: f32,
+    schedule: Schedule,
+    timeline: OnceCell<Timeline>,
 }
 
 impl VStack {
-    fn set_gap(mut self, gap: f32) -> VStack {
-        self.gap = gap;
-        self
+    fn timeline(&self) -> &Timeline {
+        self.timeline
+            .get_or_init(|| items_timeline(&self.items, &self.schedule))
     }
 }
 
 impl Widget for VStack {
     fn measure(&self) -> Point {
-        Point::new(WIDTH as f32, 5.0)
+        let sizes: Vec<Point> = self.items.iter().map(|item| item.measure()).collect();
+        let width = sizes.iter().map(|size| size.x).fold(0.0, f32::max);
+        let height = sizes.iter().map(|size| size.y).sum::<f32>()
+            + self.gap * sizes.len().saturating_sub(1) as f32;
+        Point::new(width, height)
     }
     fn frame_count(&self) -> u32 {
-        self.items
-            .iter()
-            .map(|item| item.frame_count())
-            .max()
-            .unwrap_or(1)
+        self.timeline().frame_count()
+    }
+    fn frame_duration(&self, frame: u32) -> u32 {
+        self.timeline().duration(frame)
     }
-    fn render(&self, dt: &mut DrawTarget, point: Point, frame: u32) -> Result<()> {
+    fn render(&self, dt: &mut Canvas, point: Point, frame: u32) -> Result<()> {
+        let timeline = self.timeline();
         let mut start_point = point.clone();
-        for item in self.items.iter() {
-            item.render(dt, start_point, frame)?;
+        for (i, item) in self.items.iter().enumerate() {
+            item.render(dt, start_point, timeline.child_frame(frame, i))?;
             start_point.y = start_point.y + item.measure().y + self.gap;
         }
         Ok(())
     }
 }
 
-// It's super annoying to create a Vec of things
-// that implement the Widget trait, so trying to use
-// a macro instead.
-#[macro_export]
-macro_rules! hstack {
-    ( $( $x:expr ),* ) => {
-        {
-            let mut temp_vec: Vec<Box<dyn Widget>> = Vec::new();
-            $(
-                if let Ok(z) = $x {
-                    temp_vec.push(Box::new(z));
+fn render_frames(widget: &dyn Widget, width: i32, height: i32) -> Result<Vec<Frame>> {
+    let mut frames: Vec<Frame> = Vec::new();
+    for frame in 0..widget.frame_count() {
+        let mut canvas = Canvas::new(width, height);
+        widget.render(&mut canvas, Point::new(2., 2.), frame)?;
+        frames.push(canvas.into_frame(widget.frame_duration(frame)));
+    }
+    Ok(frames)
+}
+
+fn bench(playlist: &Playlist, iterations: u32) -> Result<()> {
+    let data = Data::sample(Local::now())?;
+    // Render once first so that parsing fonts isn't counted.
+    for screen in &playlist.screens {
+        if let Some(widget) = screen.build(&data)? {
+            render_frames(widget.as_ref(), DISPLAY_WIDTH, DISPLAY_HEIGHT)?;
+        }
+    }
+    let start = Instant::now();
+    let mut frame_count = 0;
+    for _ in 0..iterations {
+        for screen in &playlist.screens {
+            if let Some(widget) = screen.build(&data)? {
+                frame_count += render_frames(widget.as_ref(), DISPLAY_WIDTH, DISPLAY_HEIGHT)?.len();
+            }
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "Rendered {} frames in {:?}, {:?} per frame",
+        frame_count,
+        elapsed,
+        elapsed / frame_count.max(1) as u32
+    );
+    Ok(())
+}
+
+// The last image pushed to the Tidbyt.
+struct LastPush {
+    fingerprint: u64,
+    at: Instant,
+}
+
+// One Tidbyt that rendered frames go to, or with --debug, its debug
+// file, or with --serve or --preview-terminal, its preview.
+struct Device {
+    // Only devices from the config have names.
+    name: Option<String>,
+    client: Option<TidbytClient>,
+    installation: String,
+    background: bool,
+    brightness: BrightnessPolicy,
+    calibration: Calibration,
+    preview: Option<Preview>,
+    terminal: Option<TerminalPreview>,
+    last_push: Option<LastPush>,
+}
+
+impl Device {
+    // Whether the frames are the same as the last push, and it
+    // was recent enough not to need forcing.
+    fn unchanged(&self, args: &Args, fingerprint: u64) -> bool {
+        self.last_push.as_ref().is_some_and(|last| {
+            last.fingerprint == fingerprint
+                && last.at.elapsed() < Duration::from_secs(args.force_push_after)
+        })
+    }
+
+    // How bright the device should be right now.
+    fn level(&self, args: &Args) -> f64 {
+        let location = home();
+        args.brightness.unwrap_or_else(|| {
+            self.brightness
+                .level(&Local::now(), location.lat, location.lon)
+        })
+    }
+
+    // Dim and calibrate, encode and push the frames, unless nothing has
+    // changed since the last push. With --debug, write to the debug file
+    // instead, with the device's name and the screen's added when there
+    // are any, like `out.kitchen.clock.webp`.
+    async fn send(&mut self, args: &Args, screen: Option<&str>, frames: &[Frame]) -> Result<()> {
+        // The level is quantized, so that it doesn't change the
+        // fingerprint on every render as the sun comes up.
+        let level = quantize(self.level(args));
+        let fingerprint = fingerprint(frames, level);
+        let frames = &self
+            .calibration
+            .apply(frames, DISPLAY_WIDTH as usize, level as f32);
+        if let Some(terminal) = &self.terminal {
+            terminal.show(frames);
+            return Ok(());
+        }
+        if args.debug.is_none() && self.preview.is_none() && self.unchanged(args, fingerprint) {
+            let name = [self.name.as_deref(), Some(screen.unwrap_or("playlist"))];
+            println!(
+                "{}: unchanged, skipping push",
+                name.iter().flatten().copied().collect::<Vec<_>>().join("/")
+            );
+            return Ok(());
+        }
+        let file_contents = encode(
+            frames,
+            DISPLAY_WIDTH as u32,
+            DISPLAY_HEIGHT as u32,
+            args.loop_count,
+        )?;
+        if let Some(preview) = &self.preview {
+            let name = self.name.as_deref().unwrap_or("tidbyt");
+            preview.show(name, screen, frames, file_contents);
+            return Ok(());
+        }
+        match &args.debug {
+            Some(filename) => {
+                let path = std::path::Path::new(filename);
+                let mut name = path.file_stem().unwrap_or_default().to_os_string();
+                for part in [self.name.as_deref(), screen].iter().flatten() {
+                    name.push(".");
+                    name.push(part);
                 }
-                if let Err(z) = $x {
-                    println!("{:?}", z);
+                if let Some(extension) = path.extension() {
+                    name.push(".");
+                    name.push(extension);
                 }
-            )*
-            let res: Result<HStack, anyhow::Error> = Ok(HStack {
-                items: temp_vec,
-                gap: 0.0,
-                expand: false
-            });
-            res
+                std::fs::write(path.with_file_name(name), file_contents)?
+            }
+            None => {
+                self.client
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("No Tidbyt to push to"))?
+                    .push(&file_contents, &self.installation, self.background)
+                    .await?;
+                self.last_push = Some(LastPush {
+                    fingerprint,
+                    at: Instant::now(),
+                });
+            }
         }
-    };
+        Ok(())
+    }
 }
 
-#[macro_export]
-macro_rules! vstack {
-    ( $( $x:expr ),* ) => {
-        {
-            let mut temp_vec: Vec<Box<dyn Widget>> = Vec::new();
-            $(
-                if let Ok(z) = $x {
-                    temp_vec.push(Box::new(z));
-                }
-            )*
-            let res: Result<VStack, anyhow::Error> = Ok(VStack { items: temp_vec, gap: 0.0 });
-            res
+// Devices that show the same playlist, so share its rendering.
+struct Group {
+    playlist: Playlist,
+    devices: Vec<Device>,
+}
+
+// Send the frames to every device, carrying on past any that fail.
+async fn send_all(args: &Args, devices: &mut [Device], screen: Option<&str>, frames: &[Frame]) {
+    for device in devices {
+        if let Err(err) = device.send(args, screen, frames).await {
+            match &device.name {
+                Some(name) => println!("{}: {:?}", name, err),
+                None => println!("{:?}", err),
+            }
         }
-    };
+    }
 }
 
-macro_rules! vstack {
-    ( $( $x:expr ),* ) => {
-        {
-            let mut temp_vec: Vec<Box<dyn Widget>> = Vec::new();
-            $(
-                if let Ok(z) = $x {
-                    temp_vec.push(Box::new(z));
-                }
-            )*
-            let res: Result<VStack, anyhow::Error> = Ok(VStack { items: temp_vec, gap: 0.0 });
-            res
+// Render every screen that has something to show into one
+// animation, each looped for its dwell.
+fn concat_frames(playlist: &Playlist, data: &Data, width: i32, height: i32) -> Result<Vec<Frame>> {
+    let mut frames: Vec<Frame> = Vec::new();
+    for screen in &playlist.screens {
+        if let Some(widget) = screen.build(data)? {
+            println!("{}: {} frames", screen.name, widget.frame_count());
+            let screen_frames = render_frames(widget.as_ref(), width, height)?;
+            frames.extend(repeat_for(&screen_frames, screen.dwell));
         }
+    }
+    if frames.is_empty() {
+        bail!("Playlist has nothing to show");
+    }
+    Ok(frames)
+}
+
+// Render the group's playlist and push it to each of its devices,
+// returning how long to wait before starting the next cycle.
+async fn render(
+    args: &Args,
+    group: &mut Group,
+    locale: &Locale,
+    readings: &watch::Receiver<Readings>,
+) -> Result<Duration> {
+    let (width, height) = (DISPLAY_WIDTH, DISPLAY_HEIGHT);
+    let Group { playlist, devices } = group;
+    let mut data = Data {
+        now: Local::now(),
+        readings: readings.borrow().clone(),
+        locale: locale.clone(),
     };
+
+    match playlist.mode {
+        Mode::Concat => {
+            let frames = concat_frames(playlist, &data, width, height)?;
+            let total: u32 = frames.iter().map(|frame| frame.duration).sum();
+            send_all(args, devices, None, &frames).await;
+            Ok(Duration::from_millis(total as u64))
+        }
+        Mode::Sequence => {
+            let mut wait: Option<Duration> = None;
+            for screen in &playlist.screens {
+                // Build each screen just before it's shown, so that
+                // clocks and readings are current when they appear.
+                data.now = Local::now();
+                data.readings = readings.borrow().clone();
+                if let Some(widget) = screen.build(&data)? {
+                    println!("{}: {} frames", screen.name, widget.frame_count());
+                    let frames = render_frames(widget.as_ref(), width, height)?;
+                    if let (Some(wait), None) = (wait, &args.debug) {
+                        sleep(wait).await;
+                    }
+                    send_all(args, devices, Some(&screen.name), &frames).await;
+                    wait = Some(Duration::from_millis(screen.dwell as u64));
+                }
+            }
+            wait.ok_or_else(|| anyhow!("Playlist has nothing to show"))
+        }
+    }
 }
 
-async fn render(args: &Args) -> Result<()> {
-    let local: DateTime<Local> = Local::now();
-    let width = 64i32;
-    let height = 32i32;
-    let mut config = WebPConfig::new().map_err(|_s| anyhow!("WebPConfig failed"))?;
-    config.lossless = 1;
-    let mut encoder = AnimEncoder::new(width as u32, height as u32, &config);
-
-    let (count, rec_chart) = get_email_count().await.unwrap_or((0, Vec::new()));
-    let (miles_today, week_miles, miles_chart) =
-        get_strava().await.unwrap_or((None, 0.0, Vec::new()));
-
-    let layout = vstack![
-        hstack![
-            get_weather().await,
-            TextWidget::new(format!("{}", local.format("%l:%M")), String::from("#fff"),)
-        ],
-        hstack![
-            TextWidget::new(format!("{} MAIL", count), String::from("#fff")),
-            ChartWidget::new(&rec_chart)
-        ],
-        hstack![
-            match miles_today {
-                None => TextWidget::new(String::from("RUN"), String::from("#fff")),
-                Some(mi) => TextWidget::new(format!("{:.0} MI", mi), String::from("#0f0")),
-            },
-            TextWidget::new(format!("{:.0} WK", week_miles), String::from("#fff")),
-            ChartWidget::new(&miles_chart)
-        ],
-        hstack![get_aqi().await, get_uv().await]
-    ]
-    .map(|s| s.set_gap(2.0));
-
-    let mut frames: Vec<Vec<u8>> = Vec::new();
-
-    if let Ok(l) = layout {
-        let frame_count = l.frame_count();
-        println!("Frame count: {:?}", frame_count);
-        for frame in 0..frame_count {
-            let mut dt = DrawTarget::new(width, height);
-            l.render(&mut dt, Point::new(2., 2.), frame)?;
-
-            let output = get_rgba(dt);
-            frames.push(output);
-        }
-    }
-
-    // Step 1: Clone all outputs
-    let cloned_outputs: Vec<_> = frames.into_iter().map(|output| output.clone()).collect();
-
-    // Step 2: Create AnimFrames
-    let frames: Vec<_> = cloned_outputs
-        .iter()
-        .map(|rgba| AnimFrame::from_rgba(rgba, width as u32, height as u32, 0))
-        .collect();
-
-    // Step 3: Add each frame to the encoder
-    let f: Vec<_> = frames
-        .into_iter()
-        .map(|frame| encoder.add_frame(frame))
-        .collect();
-
-    let file_contents = encoder.encode().to_vec();
-
-    if let Some(filename) = &args.debug {
-        std::fs::write(filename, file_contents)?;
-    } else {
-        push(&file_contents).await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aqi::aqi::AqiSource;
+    use source::source::Reading;
+
+    // A marquee `overflow` pixels narrower than its text.
+    fn marquee(overflow: f32) -> MarqueeWidget {
+        let text = TextWidget::new(String::from("A long forecast"), String::from("text")).unwrap();
+        let width = text.measure().x - overflow;
+        MarqueeWidget::new(text, width)
+            .unwrap()
+            .set_speed(2, 100)
+            .set_pauses(1000, 500)
+    }
+
+    fn frames(widget: &MarqueeWidget) -> Vec<(f32, u32)> {
+        (0..widget.frame_count())
+            .map(|frame| (widget.offset(frame), widget.frame_duration(frame)))
+            .collect()
+    }
+
+    #[test]
+    fn marquees_pause_at_the_start_and_end() {
+        // The last step stops at the end rather than overshooting.
+        assert_eq!(
+            frames(&marquee(5.0)),
+            [(0.0, 1100), (2.0, 100), (4.0, 100), (5.0, 600)]
+        );
+    }
+
+    #[test]
+    fn marquees_that_fit_hold_still() {
+        let text = TextWidget::new(String::from("Hi"), String::from("text")).unwrap();
+        let widget = MarqueeWidget::new(text, 64.0).unwrap();
+        assert_eq!(frames(&widget), [(0.0, DEFAULT_FRAME_DURATION)]);
+    }
+
+    #[test]
+    fn marquees_wrap_around_past_the_end() {
+        let widget = marquee(5.0).set_wrap(true, 4.0);
+        let period = widget.period();
+        assert_eq!(period as f32, (widget.text.measure().x + 4.0).ceil());
+        let frames = frames(&widget);
+        assert_eq!(
+            frames[..4],
+            [(0.0, 1100), (2.0, 100), (4.0, 100), (5.0, 600)]
+        );
+        // Then on from the end, a step at a time, until the start
+        // is back where it began, which is the first frame again.
+        let rest: Vec<(f32, u32)> = (7..period).step_by(2).map(|x| (x as f32, 100)).collect();
+        assert_eq!(frames[4..], rest);
+        assert!(frames.last().unwrap().0 as u32 + 2 >= period);
+        assert_eq!(widget.offset(frames.len() as u32), 0.0);
+    }
+
+    #[test]
+    fn marquee_steps_follow_the_setters() {
+        let widget = marquee(5.0);
+        assert_eq!(widget.frame_count(), 4);
+        let widget = widget.set_speed(1, 50);
+        assert_eq!(widget.frame_count(), 6);
+        assert_eq!(widget.frame_duration(5), 550);
+    }
+
+    const PLAYLIST: &str = r#"{
+        "screens": [
+            {"name": "clock", "dwell": 3000, "layout": {"type": "clock"}},
+            {"name": "air", "dwell": 2000, "when": "aqi", "layout": {"type": "text", "source": "aqi"}},
+            {"name": "mail", "dwell": 1500, "layout": {"type": "text", "source": "mail"}}
+        ]
+    }"#;
+
+    fn total(frames: &[Frame]) -> u32 {
+        frames.iter().map(|frame| frame.duration).sum()
+    }
+
+    #[test]
+    fn concat_shows_each_screen_for_its_dwell() {
+        let playlist = Playlist::parse(PLAYLIST).unwrap();
+        let data = Data::sample(Local.with_ymd_and_hms(2026, 10, 18, 9, 30, 0).unwrap()).unwrap();
+        let frames = concat_frames(&playlist, &data, 64, 32).unwrap();
+        assert_eq!(total(&frames), 3000 + 2000 + 1500);
+    }
+
+    #[test]
+    fn concat_leaves_out_screens_whose_when_is_hidden() {
+        let playlist = Playlist::parse(PLAYLIST).unwrap();
+        let mut data =
+            Data::sample(Local.with_ymd_and_hms(2026, 10, 18, 9, 30, 0).unwrap()).unwrap();
+        data.readings.insert::<AqiSource>(Reading::Hidden).unwrap();
+        let frames = concat_frames(&playlist, &data, 64, 32).unwrap();
+        assert_eq!(total(&frames), 3000 + 1500);
+    }
+
+    #[test]
+    fn concat_fails_with_nothing_to_show() {
+        let playlist = Playlist::parse(
+            r#"{"screens": [{"name": "air", "when": "aqi", "layout": {"type": "clock"}}]}"#,
+        )
+        .unwrap();
+        let mut data =
+            Data::sample(Local.with_ymd_and_hms(2026, 10, 18, 9, 30, 0).unwrap()).unwrap();
+        data.readings.insert::<AqiSource>(Reading::Hidden).unwrap();
+        let Err(err) = concat_frames(&playlist, &data, 64, 32) else {
+            panic!("a playlist with nothing to show rendered");
+        };
+        assert_eq!(err.to_string(), "Playlist has nothing to show");
     }
-    Ok(())
 }
@@ -0,0 +1,252 @@
+/// A rotation of named screens, each a layout shown for a while.
+pub mod playlist {
+    use crate::layout::layout::{source_shown, Data, Layout, TEXT_SOURCES};
+    use crate::Widget;
+    use anyhow::{bail, Context, Result};
+    use serde::Deserialize;
+    use serde_json::Value;
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    /// The playlist that ships with the binary, used when
+    /// neither `--playlist` nor `--layout` is given.
+    pub const DEFAULT_PLAYLIST: &str = include_str!("../layouts/playlist.json");
+
+    /// How long a screen shows for when the playlist doesn't say, in ms.
+    pub const DEFAULT_DWELL: u32 = 10_000;
+
+    fn default_dwell() -> u32 {
+        DEFAULT_DWELL
+    }
+
+    /// How the screens get to the device.
+    #[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Mode {
+        /// One animation with every screen in turn, pushed once a cycle.
+        #[default]
+        Concat,
+        /// A push for each screen, each once the one before has had its dwell.
+        Sequence,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct ScreenFile {
+        name: String,
+        #[serde(default = "default_dwell")]
+        dwell: u32,
+        /// A text source that must have something to show for the screen to.
+        #[serde(default)]
+        when: Option<String>,
+        layout: Value,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct PlaylistFile {
+        #[serde(default)]
+        mode: Mode,
+        screens: Vec<ScreenFile>,
+    }
+
+    #[derive(Debug)]
+    pub struct Screen {
+        pub name: String,
+        /// How long the screen shows for, in ms.
+        pub dwell: u32,
+        when: Option<String>,
+        layout: Layout,
+    }
+
+    /// Screens that have been parsed and checked, shown in order.
+    #[derive(Debug)]
+    pub struct Playlist {
+        pub mode: Mode,
+        pub screens: Vec<Screen>,
+    }
+
+    impl Screen {
+        /// Build the screen, or `None` if its condition isn't met
+        /// or nothing on it has anything to show.
+        pub(crate) fn build(&self, data: &Data) -> Result<Option<Box<dyn Widget>>> {
+            if let Some(source) = &self.when {
+                if !source_shown(source, data)? {
+                    return Ok(None);
+                }
+            }
+            self.layout.build(data)
+        }
+    }
+
+    impl Playlist {
+        pub fn parse(json: &str) -> Result<Playlist> {
+            let file: PlaylistFile =
+                serde_json::from_str(json).context("Could not parse playlist")?;
+            if file.screens.is_empty() {
+                bail!("playlist has no screens");
+            }
+            let mut names = HashSet::new();
+            let mut screens = Vec::new();
+            for (i, screen) in file.screens.into_iter().enumerate() {
+                let path = format!("screens[{}]", i);
+                if !names.insert(screen.name.clone()) {
+                    bail!("{}: there's already a screen named `{}`", path, screen.name);
+                }
+                if screen.dwell == 0 {
+                    bail!("{} ({}): dwell must be at least 1ms", path, screen.name);
+                }
+                if let Some(source) = &screen.when {
+                    if !TEXT_SOURCES.contains(&source.as_str()) {
+                        bail!(
+                            "{} ({}): unknown source `{}` in `when`, expected one of {}",
+                            path,
+                            screen.name,
+                            source,
+                            TEXT_SOURCES.join(", ")
+                        );
+                    }
+                }
+                screens.push(Screen {
+                    layout: Layout::from_value(screen.layout, &format!("{}.layout", path))?,
+                    name: screen.name,
+                    dwell: screen.dwell,
+                    when: screen.when,
+                });
+            }
+            Ok(Playlist {
+                mode: file.mode,
+                screens,
+            })
+        }
+
+        pub fn load(path: &Path) -> Result<Playlist> {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Could not read playlist {}", path.display()))?;
+            Playlist::parse(&json).with_context(|| format!("Invalid playlist {}", path.display()))
+        }
+
+        /// A playlist of one layout, which shows on its own the
+        /// way it would without a playlist.
+        pub fn single(layout: Layout) -> Playlist {
+            Playlist {
+                mode: Mode::Concat,
+                screens: vec![Screen {
+                    name: String::from("layout"),
+                    dwell: DEFAULT_DWELL,
+                    when: None,
+                    layout,
+                }],
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::aqi::aqi::AqiSource;
+        use crate::source::source::Reading;
+        use chrono::{Local, TimeZone};
+
+        fn error(json: &str) -> String {
+            format!("{:#}", Playlist::parse(json).unwrap_err())
+        }
+
+        #[test]
+        fn invalid_playlists_are_rejected() {
+            assert!(error("{").starts_with("Could not parse playlist"));
+            assert!(
+                error(r#"{"screens": [], "shuffle": true}"#).contains("unknown field `shuffle`")
+            );
+            assert!(
+                error(r#"{"mode": "random", "screens": []}"#).contains("unknown variant `random`")
+            );
+            assert_eq!(error(r#"{"screens": []}"#), "playlist has no screens");
+            assert_eq!(
+                error(
+                    r#"{"screens": [
+                        {"name": "clock", "layout": {"type": "clock"}},
+                        {"name": "clock", "layout": {"type": "date"}}
+                    ]}"#
+                ),
+                "screens[1]: there's already a screen named `clock`"
+            );
+            assert_eq!(
+                error(
+                    r#"{"screens": [{"name": "clock", "dwell": 0, "layout": {"type": "clock"}}]}"#
+                ),
+                "screens[0] (clock): dwell must be at least 1ms"
+            );
+            assert!(error(
+                r#"{"screens": [{"name": "air", "when": "pollen", "layout": {"type": "clock"}}]}"#
+            )
+            .starts_with("screens[0] (air): unknown source `pollen` in `when`"));
+        }
+
+        #[test]
+        fn layout_errors_name_the_screen() {
+            let err = error(
+                r#"{"screens": [
+                    {"name": "clock", "layout": {"type": "clock"}},
+                    {"name": "air", "layout": {"type": "vstack", "items": [{"type": "chart", "source": "aqi"}]}}
+                ]}"#,
+            );
+            assert!(
+                err.starts_with("screens[1].layout.items[0] (chart): unknown source `aqi`"),
+                "{}",
+                err
+            );
+        }
+
+        #[test]
+        fn loading_names_the_file() {
+            let missing = Path::new("layouts/missing.json");
+            let err = format!("{:#}", Playlist::load(missing).unwrap_err());
+            assert!(
+                err.starts_with("Could not read playlist layouts/missing.json"),
+                "{}",
+                err
+            );
+            // A layout on its own isn't a playlist.
+            let layout = Path::new(env!("CARGO_MANIFEST_DIR")).join("layouts/default.json");
+            let err = format!("{:#}", Playlist::load(&layout).unwrap_err());
+            assert!(
+                err.starts_with(&format!("Invalid playlist {}", layout.display())),
+                "{}",
+                err
+            );
+        }
+
+        #[test]
+        fn dwell_defaults_when_not_given() {
+            let playlist = Playlist::parse(
+                r#"{"screens": [
+                    {"name": "clock", "layout": {"type": "clock"}},
+                    {"name": "date", "dwell": 2500, "layout": {"type": "date"}}
+                ]}"#,
+            )
+            .unwrap();
+            assert_eq!(playlist.mode, Mode::Concat);
+            let dwells: Vec<(&str, u32)> = playlist
+                .screens
+                .iter()
+                .map(|screen| (screen.name.as_str(), screen.dwell))
+                .collect();
+            assert_eq!(dwells, [("clock", DEFAULT_DWELL), ("date", 2500)]);
+        }
+
+        #[test]
+        fn screens_show_only_when_their_source_does() {
+            let playlist = Playlist::parse(
+                r#"{"screens": [{"name": "air", "when": "aqi", "layout": {"type": "clock"}}]}"#,
+            )
+            .unwrap();
+            let screen = &playlist.screens[0];
+            let mut data =
+                Data::sample(Local.with_ymd_and_hms(2026, 10, 18, 9, 30, 0).unwrap()).unwrap();
+            assert!(screen.build(&data).unwrap().is_some());
+            data.readings.insert::<AqiSource>(Reading::Hidden).unwrap();
+            assert!(screen.build(&data).unwrap().is_none());
+        }
+    }
+}
@@ -1,44 +1,496 @@
+/// Pushing images to a Tidbyt, and managing what's installed on it.
 pub mod pusher {
-    use anyhow::Result;
-    use reqwest::header::USER_AGENT;
-    use serde::Serialize;
+    use reqwest::header::{RETRY_AFTER, USER_AGENT};
+    use reqwest::{Method, RequestBuilder, Response, StatusCode};
+    use serde::{Deserialize, Serialize};
     use std::env;
+    use std::time::Duration;
+    use thiserror::Error;
+    use tokio::time::sleep;
 
     use base64::{engine::general_purpose, Engine as _};
 
+    const API: &str = "https://api.tidbyt.com";
+
+    /// How many times to try a request that fails with a 5xx or 429,
+    /// or can't connect or times out.
+    const MAX_ATTEMPTS: u32 = 4;
+
+    /// How long to wait before the first retry, doubling after each.
+    const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+    /// The longest to wait between tries, whatever `Retry-After` asks
+    /// for, since pushes hold up the render loop.
+    const MAX_WAIT: Duration = Duration::from_secs(30);
+
+    /// How long a request can take before it's given up on.
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// The installation pushes go to unless told otherwise.
+    pub const DEFAULT_INSTALLATION: &str = "custom";
+
+    #[derive(Debug, Error)]
+    pub enum PushError {
+        #[error("{0} is not set")]
+        MissingCredential(&'static str),
+        #[error("installation ID `{0}` must be letters and numbers only")]
+        InvalidInstallation(String),
+        #[error("could not reach the Tidbyt API: {0}")]
+        Request(#[from] reqwest::Error),
+        #[error("the Tidbyt API returned {status}: {body}")]
+        Status { status: StatusCode, body: String },
+    }
+
     #[derive(Serialize, Debug)]
-    struct TidbytPayload {
-        deviceID: String,
-        installationID: String,
+    struct PushPayload<'a> {
+        #[serde(rename = "installationID")]
+        installation_id: &'a str,
         image: String,
         background: bool,
     }
 
-    pub async fn push(file_contents: &Vec<u8>) -> Result<bool> {
-        let base64_string = general_purpose::STANDARD.encode(&file_contents);
-        let device_id = env::var("TIDBYT_ID").expect("Missing TIDBYT_ID");
-        let tidbyt_key = env::var("TIDBYT_KEY").expect("Missing TIDBYT_KEY");
-        let endpoint = format!("https://api.tidbyt.com/v0/devices/{}/push", device_id);
+    /// Something installed on the device, either an app
+    /// from the Tidbyt community or a pushed image.
+    #[derive(Deserialize, Debug, Clone)]
+    pub struct Installation {
+        pub id: String,
+        #[serde(rename = "appID", default)]
+        pub app_id: String,
+    }
 
-        let payload = TidbytPayload {
-            deviceID: device_id,
-            image: base64_string.clone(),
-            installationID: "custom".into(),
-            background: true,
-        };
+    #[derive(Deserialize, Debug)]
+    struct Installations {
+        installations: Vec<Installation>,
+    }
 
-        let resp = reqwest::Client::new()
-            .post(&endpoint)
-            .bearer_auth(tidbyt_key)
-            .json(&payload)
-            .header(USER_AGENT, "tidbyt")
-            .send()
-            .await?;
+    /// Tidbyt only accepts alphanumeric installation IDs.
+    fn validate_installation(id: &str) -> Result<(), PushError> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(PushError::InvalidInstallation(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Whether a request that got this status is worth trying again.
+    fn retryable(status: StatusCode) -> bool {
+        status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+    }
+
+    /// Whether a request that failed without a response is worth trying
+    /// again. Connecting and timing out can be a blip, but anything
+    /// else, like a bad URL, will fail the same way next time.
+    fn retryable_error(err: &reqwest::Error) -> bool {
+        err.is_connect() || err.is_timeout()
+    }
 
-        if resp.status().as_u16() != 200 {
-            println!("{:?}", resp.text().await.unwrap());
+    /// How long a 429 asks us to wait, if it says in seconds.
+    fn retry_after(response: &Response) -> Option<Duration> {
+        let seconds = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+        seconds.trim().parse().ok().map(Duration::from_secs)
+    }
+
+    /// A client for one device in the Tidbyt API.
+    pub struct TidbytClient {
+        client: reqwest::Client,
+        base_url: String,
+        device_id: String,
+        key: String,
+        max_attempts: u32,
+        initial_backoff: Duration,
+        max_wait: Duration,
+        timeout: Duration,
+    }
+
+    impl TidbytClient {
+        pub fn new(device_id: String, key: String) -> TidbytClient {
+            TidbytClient {
+                client: reqwest::Client::new(),
+                base_url: String::from(API),
+                device_id,
+                key,
+                max_attempts: MAX_ATTEMPTS,
+                initial_backoff: INITIAL_BACKOFF,
+                max_wait: MAX_WAIT,
+                timeout: TIMEOUT,
+            }
         }
 
-        Ok(true)
+        /// A client for the device in `TIDBYT_ID`, using the API key in `TIDBYT_KEY`.
+        pub fn from_env() -> Result<TidbytClient, PushError> {
+            let credential =
+                |name: &'static str| env::var(name).map_err(|_| PushError::MissingCredential(name));
+            Ok(TidbytClient::new(
+                credential("TIDBYT_ID")?,
+                credential("TIDBYT_KEY")?,
+            ))
+        }
+
+        /// Talk to another server, like a local mock of the API.
+        pub fn set_base_url(mut self, base_url: &str) -> TidbytClient {
+            self.base_url = base_url.trim_end_matches('/').to_string();
+            self
+        }
+
+        pub fn set_retries(mut self, max_attempts: u32, initial_backoff: Duration) -> TidbytClient {
+            self.max_attempts = max_attempts.max(1);
+            self.initial_backoff = initial_backoff;
+            self
+        }
+
+        /// Cap the wait between tries, including any `Retry-After`.
+        pub fn set_max_wait(mut self, max_wait: Duration) -> TidbytClient {
+            self.max_wait = max_wait;
+            self
+        }
+
+        pub fn set_timeout(mut self, timeout: Duration) -> TidbytClient {
+            self.timeout = timeout;
+            self
+        }
+
+        fn request(&self, method: Method, path: &str) -> RequestBuilder {
+            self.client
+                .request(
+                    method,
+                    format!("{}/v0/devices/{}{}", self.base_url, self.device_id, path),
+                )
+                .bearer_auth(&self.key)
+                .header(USER_AGENT, "tidbyt")
+                .timeout(self.timeout)
+        }
+
+        /// Send a request, retrying with exponential backoff while the
+        /// API is failing, rate limiting us or can't be reached, and
+        /// turning anything else that isn't a success into an error.
+        async fn send(&self, build: impl Fn() -> RequestBuilder) -> Result<Response, PushError> {
+            let mut backoff = self.initial_backoff;
+            let mut attempt = 1;
+            loop {
+                let last = attempt >= self.max_attempts;
+                let wait = match build().send().await {
+                    Ok(response) if response.status().is_success() => return Ok(response),
+                    Ok(response) => {
+                        let status = response.status();
+                        if !retryable(status) || last {
+                            let body = response.text().await.unwrap_or_default();
+                            return Err(PushError::Status { status, body });
+                        }
+                        let wait = retry_after(&response)
+                            .unwrap_or(backoff)
+                            .max(backoff)
+                            .min(self.max_wait);
+                        println!("Tidbyt API returned {}, trying again in {:?}", status, wait);
+                        wait
+                    }
+                    Err(err) => {
+                        if !retryable_error(&err) || last {
+                            return Err(err.into());
+                        }
+                        let wait = backoff.min(self.max_wait);
+                        println!(
+                            "Could not reach the Tidbyt API, trying again in {:?}: {}",
+                            wait, err
+                        );
+                        wait
+                    }
+                };
+                sleep(wait).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+        }
+
+        /// Show an image on the device. A background push updates the
+        /// installation without switching the display over to it.
+        pub async fn push(
+            &self,
+            image: &[u8],
+            installation_id: &str,
+            background: bool,
+        ) -> Result<(), PushError> {
+            validate_installation(installation_id)?;
+            let payload = PushPayload {
+                installation_id,
+                image: general_purpose::STANDARD.encode(image),
+                background,
+            };
+            self.send(|| self.request(Method::POST, "/push").json(&payload))
+                .await?;
+            Ok(())
+        }
+
+        pub async fn installations(&self) -> Result<Vec<Installation>, PushError> {
+            let response = self
+                .send(|| self.request(Method::GET, "/installations"))
+                .await?;
+            Ok(response.json::<Installations>().await?.installations)
+        }
+
+        pub async fn delete_installation(&self, installation_id: &str) -> Result<(), PushError> {
+            validate_installation(installation_id)?;
+            let path = format!("/installations/{}", installation_id);
+            self.send(|| self.request(Method::DELETE, &path)).await?;
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::{Body, Request, Server};
+        use std::collections::VecDeque;
+        use std::convert::Infallible;
+        use std::net::{SocketAddr, TcpListener};
+        use std::sync::{Arc, Mutex};
+        use std::time::Instant;
+
+        /// A response for the mock API to give.
+        struct Reply {
+            status: u16,
+            headers: Vec<(&'static str, &'static str)>,
+            body: &'static str,
+            delay: Duration,
+        }
+
+        fn reply(status: u16) -> Reply {
+            Reply {
+                status,
+                headers: Vec::new(),
+                body: "",
+                delay: Duration::ZERO,
+            }
+        }
+
+        /// A request the mock API got.
+        #[derive(Debug, Clone)]
+        struct Received {
+            method: String,
+            path: String,
+            authorization: Option<String>,
+            body: String,
+        }
+
+        /// A local server that gives `replies` in order, then 500s.
+        async fn mock(replies: Vec<Reply>) -> (String, Arc<Mutex<Vec<Received>>>) {
+            let replies = Arc::new(Mutex::new(VecDeque::from(replies)));
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let log = received.clone();
+            let make_service = make_service_fn(move |_| {
+                let (replies, log) = (replies.clone(), log.clone());
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                        let (replies, log) = (replies.clone(), log.clone());
+                        async move {
+                            let method = request.method().to_string();
+                            let path = request.uri().path().to_string();
+                            let authorization = request
+                                .headers()
+                                .get("authorization")
+                                .and_then(|value| value.to_str().ok())
+                                .map(String::from);
+                            let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                            log.lock().unwrap().push(Received {
+                                method,
+                                path,
+                                authorization,
+                                body: String::from_utf8_lossy(&body).to_string(),
+                            });
+                            let next = replies.lock().unwrap().pop_front();
+                            let reply = next.unwrap_or_else(|| reply(500));
+                            sleep(reply.delay).await;
+                            let mut response = hyper::Response::builder().status(reply.status);
+                            for (name, value) in reply.headers {
+                                response = response.header(name, value);
+                            }
+                            Ok::<_, Infallible>(response.body(Body::from(reply.body)).unwrap())
+                        }
+                    }))
+                }
+            });
+            let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+            let url = format!("http://{}", server.local_addr());
+            tokio::spawn(server);
+            (url, received)
+        }
+
+        fn client(url: &str) -> TidbytClient {
+            TidbytClient::new(String::from("device"), String::from("key"))
+                .set_base_url(url)
+                .set_retries(3, Duration::from_millis(10))
+        }
+
+        fn requests(received: &Arc<Mutex<Vec<Received>>>) -> Vec<Received> {
+            received.lock().unwrap().clone()
+        }
+
+        fn status(result: Result<(), PushError>) -> u16 {
+            match result {
+                Err(PushError::Status { status, .. }) => status.as_u16(),
+                other => panic!("expected a status error, got {:?}", other),
+            }
+        }
+
+        #[tokio::test]
+        async fn pushes_the_image_as_json() {
+            let (url, received) = mock(vec![reply(200)]).await;
+            client(&url).push(b"webp", "custom", true).await.unwrap();
+            let requests = requests(&received);
+            assert_eq!(requests.len(), 1);
+            assert_eq!(requests[0].method, "POST");
+            assert_eq!(requests[0].path, "/v0/devices/device/push");
+            assert_eq!(requests[0].authorization.as_deref(), Some("Bearer key"));
+            let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+            assert_eq!(
+                body,
+                serde_json::json!({
+                    "installationID": "custom",
+                    "image": general_purpose::STANDARD.encode(b"webp"),
+                    "background": true,
+                })
+            );
+        }
+
+        #[tokio::test]
+        async fn retries_server_errors() {
+            let (url, received) = mock(vec![reply(503), reply(502), reply(200)]).await;
+            client(&url).push(b"webp", "custom", false).await.unwrap();
+            assert_eq!(requests(&received).len(), 3);
+        }
+
+        #[tokio::test]
+        async fn retries_rate_limits() {
+            let (url, received) = mock(vec![reply(429), reply(200)]).await;
+            client(&url).push(b"webp", "custom", false).await.unwrap();
+            assert_eq!(requests(&received).len(), 2);
+        }
+
+        #[tokio::test]
+        async fn gives_up_after_the_last_attempt() {
+            let mut last = reply(503);
+            last.body = "down for maintenance";
+            let (url, received) = mock(vec![reply(503), reply(503), last, reply(200)]).await;
+            match client(&url).push(b"webp", "custom", false).await {
+                Err(PushError::Status { status, body }) => {
+                    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+                    assert_eq!(body, "down for maintenance");
+                }
+                other => panic!("expected a status error, got {:?}", other),
+            }
+            assert_eq!(requests(&received).len(), 3);
+        }
+
+        #[tokio::test]
+        async fn does_not_retry_client_errors() {
+            for code in [400, 401, 404] {
+                let (url, received) = mock(vec![reply(code), reply(200)]).await;
+                assert_eq!(
+                    status(client(&url).push(b"webp", "custom", false).await),
+                    code
+                );
+                assert_eq!(requests(&received).len(), 1, "retried a {}", code);
+            }
+        }
+
+        #[tokio::test]
+        async fn waits_as_long_as_retry_after_says() {
+            let mut limited = reply(429);
+            limited.headers.push(("retry-after", "1"));
+            let (url, received) = mock(vec![limited, reply(200)]).await;
+            let start = Instant::now();
+            client(&url).push(b"webp", "custom", false).await.unwrap();
+            assert!(start.elapsed() >= Duration::from_secs(1));
+            assert_eq!(requests(&received).len(), 2);
+        }
+
+        #[tokio::test]
+        async fn caps_the_retry_after_wait() {
+            let mut limited = reply(429);
+            limited.headers.push(("retry-after", "3600"));
+            let (url, received) = mock(vec![limited, reply(200)]).await;
+            let start = Instant::now();
+            client(&url)
+                .set_max_wait(Duration::from_millis(50))
+                .push(b"webp", "custom", false)
+                .await
+                .unwrap();
+            assert!(start.elapsed() < Duration::from_secs(5));
+            assert_eq!(requests(&received).len(), 2);
+        }
+
+        #[tokio::test]
+        async fn retries_timeouts() {
+            let mut slow = reply(200);
+            slow.delay = Duration::from_secs(2);
+            let (url, received) = mock(vec![slow, reply(200)]).await;
+            client(&url)
+                .set_timeout(Duration::from_millis(200))
+                .push(b"webp", "custom", false)
+                .await
+                .unwrap();
+            assert_eq!(requests(&received).len(), 2);
+        }
+
+        #[tokio::test]
+        async fn retries_connection_failures() {
+            // Nothing listens on a port that was just freed.
+            let port = TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port();
+            let start = Instant::now();
+            let result = client(&format!("http://127.0.0.1:{}", port))
+                .push(b"webp", "custom", false)
+                .await;
+            assert!(matches!(result, Err(PushError::Request(ref err)) if err.is_connect()));
+            // Waiting 10ms, then 20ms, between the three attempts.
+            assert!(start.elapsed() >= Duration::from_millis(30));
+        }
+
+        #[tokio::test]
+        async fn lists_installations() {
+            let mut listed = reply(200);
+            listed.body =
+                r#"{"installations": [{"id": "custom"}, {"id": "clock", "appID": "analogclock"}]}"#;
+            let (url, received) = mock(vec![listed]).await;
+            let installations = client(&url).installations().await.unwrap();
+            let ids: Vec<(&str, &str)> = installations
+                .iter()
+                .map(|installation| (installation.id.as_str(), installation.app_id.as_str()))
+                .collect();
+            assert_eq!(ids, [("custom", ""), ("clock", "analogclock")]);
+            let requests = requests(&received);
+            assert_eq!(requests[0].method, "GET");
+            assert_eq!(requests[0].path, "/v0/devices/device/installations");
+        }
+
+        #[tokio::test]
+        async fn fails_on_an_unexpected_installations_body() {
+            let mut listed = reply(200);
+            listed.body = r#"{"apps": []}"#;
+            let (url, _) = mock(vec![listed]).await;
+            let result = client(&url).installations().await;
+            assert!(matches!(result, Err(PushError::Request(ref err)) if err.is_decode()));
+        }
+
+        #[tokio::test]
+        async fn deletes_installations() {
+            let (url, received) = mock(vec![reply(200)]).await;
+            client(&url).delete_installation("custom").await.unwrap();
+            let requests = requests(&received);
+            assert_eq!(requests[0].method, "DELETE");
+            assert_eq!(requests[0].path, "/v0/devices/device/installations/custom");
+        }
+
+        #[tokio::test]
+        async fn checks_installation_ids_before_sending() {
+            let (url, received) = mock(vec![]).await;
+            let result = client(&url).delete_installation("not-valid").await;
+            assert!(matches!(result, Err(PushError::InvalidInstallation(_))));
+            let result = client(&url).push(b"webp", "", false).await;
+            assert!(matches!(result, Err(PushError::InvalidInstallation(_))));
+            assert!(requests(&received).is_empty());
+        }
     }
 }
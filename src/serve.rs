@@ -0,0 +1,214 @@
+/// A local web page that previews what would be pushed, for
+/// working on layouts without a Tidbyt.
+pub mod serve {
+    use crate::animation::animation::Frame;
+    use crate::source::source::Readings;
+    use anyhow::{anyhow, Result};
+    use chrono::{DateTime, Utc};
+    use hyper::header::{CACHE_CONTROL, CONTENT_TYPE};
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Method, Request, Response, Server, StatusCode};
+    use reqwest::Url;
+    use serde::Serialize;
+    use serde_json::{json, Map, Value};
+    use std::collections::BTreeMap;
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::watch;
+
+    const PAGE: &str = include_str!("../index.html");
+
+    /// The last thing rendered for one device.
+    struct Shown {
+        screen: Option<String>,
+        frames: Vec<Frame>,
+        webp: Vec<u8>,
+        updated_at: DateTime<Utc>,
+    }
+
+    /// What each device would be showing, shared between
+    /// the render loops and the server.
+    #[derive(Clone)]
+    pub struct Preview {
+        devices: Arc<Mutex<BTreeMap<String, Shown>>>,
+        width: u32,
+        height: u32,
+    }
+
+    /// A device in `/devices.json`.
+    #[derive(Serialize)]
+    struct DeviceSummary<'a> {
+        name: &'a str,
+        screen: Option<&'a str>,
+        updated_at: DateTime<Utc>,
+        /// How long each frame shows for, in ms.
+        frames: Vec<u32>,
+    }
+
+    impl Preview {
+        pub fn new(width: u32, height: u32) -> Preview {
+            Preview {
+                devices: Arc::default(),
+                width,
+                height,
+            }
+        }
+
+        /// Replace what `device` is showing with the frames, and
+        /// the animation they were encoded into.
+        pub fn show(&self, device: &str, screen: Option<&str>, frames: &[Frame], webp: Vec<u8>) {
+            let shown = Shown {
+                screen: screen.map(String::from),
+                frames: frames.to_vec(),
+                webp,
+                updated_at: Utc::now(),
+            };
+            if let Ok(mut devices) = self.devices.lock() {
+                devices.insert(String::from(device), shown);
+            }
+        }
+
+        fn summaries(&self) -> Result<Vec<u8>> {
+            let devices = self
+                .devices
+                .lock()
+                .map_err(|_| anyhow!("preview lock poisoned"))?;
+            let summaries: Vec<DeviceSummary> = devices
+                .iter()
+                .map(|(name, shown)| DeviceSummary {
+                    name,
+                    screen: shown.screen.as_deref(),
+                    updated_at: shown.updated_at,
+                    frames: shown.frames.iter().map(|frame| frame.duration).collect(),
+                })
+                .collect();
+            Ok(serde_json::to_vec(&summaries)?)
+        }
+
+        fn webp(&self, device: &str) -> Option<Vec<u8>> {
+            let devices = self.devices.lock().ok()?;
+            Some(devices.get(device)?.webp.clone())
+        }
+
+        /// One frame as a PNG, for stepping through an animation.
+        fn png(&self, device: &str, frame: usize) -> Result<Option<Vec<u8>>> {
+            let devices = self
+                .devices
+                .lock()
+                .map_err(|_| anyhow!("preview lock poisoned"))?;
+            let Some(frame) = devices
+                .get(device)
+                .and_then(|shown| shown.frames.get(frame))
+            else {
+                return Ok(None);
+            };
+            let mut png = Vec::new();
+            let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header()?.write_image_data(&frame.rgba)?;
+            Ok(Some(png))
+        }
+    }
+
+    /// Every source's last reading, when it was fetched, why the
+    /// last fetch failed if it did, and whether it's shown as stale.
+    fn sources(readings: &Readings) -> Result<Vec<u8>> {
+        let now = Utc::now();
+        let mut sources = Map::new();
+        for (name, state) in readings.states() {
+            let mut value = serde_json::to_value(state)?;
+            if let Value::Object(fields) = &mut value {
+                fields.insert(
+                    String::from("stale"),
+                    json!(readings.stale(name, now, None)),
+                );
+            }
+            sources.insert(name.clone(), value);
+        }
+        Ok(serde_json::to_vec_pretty(&Value::Object(sources))?)
+    }
+
+    fn reply(status: StatusCode, content_type: &str, body: Vec<u8>) -> Response<Body> {
+        Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, content_type)
+            .header(CACHE_CONTROL, "no-store")
+            .body(Body::from(body))
+            .unwrap_or_default()
+    }
+
+    fn not_found() -> Response<Body> {
+        reply(StatusCode::NOT_FOUND, "text/plain", b"Not found".to_vec())
+    }
+
+    fn respond(
+        request: &Request<Body>,
+        preview: &Preview,
+        readings: &watch::Receiver<Readings>,
+    ) -> Result<Response<Body>> {
+        if request.method() != Method::GET {
+            return Ok(reply(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "text/plain",
+                b"Only GET is supported".to_vec(),
+            ));
+        }
+        let url = Url::parse("http://localhost/")?.join(&request.uri().to_string())?;
+        let query = |key: &str| {
+            url.query_pairs()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.into_owned())
+        };
+        let device = query("device").unwrap_or_default();
+        Ok(match url.path() {
+            "/" => reply(StatusCode::OK, "text/html", PAGE.as_bytes().to_vec()),
+            "/devices.json" => reply(StatusCode::OK, "application/json", preview.summaries()?),
+            "/sources.json" => reply(
+                StatusCode::OK,
+                "application/json",
+                sources(&readings.borrow())?,
+            ),
+            "/preview.webp" => match preview.webp(&device) {
+                Some(webp) => reply(StatusCode::OK, "image/webp", webp),
+                None => not_found(),
+            },
+            "/frame.png" => {
+                let frame = query("frame").and_then(|frame| frame.parse().ok());
+                match frame.map(|frame| preview.png(&device, frame)).transpose()? {
+                    Some(Some(png)) => reply(StatusCode::OK, "image/png", png),
+                    _ => not_found(),
+                }
+            }
+            _ => not_found(),
+        })
+    }
+
+    /// Serve the preview page and its JSON on `addr` until the process exits.
+    pub async fn serve(
+        addr: SocketAddr,
+        preview: Preview,
+        readings: watch::Receiver<Readings>,
+    ) -> Result<()> {
+        let make_service = make_service_fn(move |_| {
+            let (preview, readings) = (preview.clone(), readings.clone());
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let response = respond(&request, &preview, &readings).unwrap_or_else(|err| {
+                        reply(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            "text/plain",
+                            format!("{:#}", err).into_bytes(),
+                        )
+                    });
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+        let server = Server::try_bind(&addr)?.serve(make_service);
+        println!("Previewing on http://{}", server.local_addr());
+        server.await?;
+        Ok(())
+    }
+}
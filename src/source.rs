@@ -0,0 +1,471 @@
+/// The places the display gets its data from, each fetched on its own
+/// schedule and kept between renders.
+pub mod source {
+    use anyhow::{anyhow, Result};
+    use async_trait::async_trait;
+    use chrono::{DateTime, Duration, Utc};
+    use futures::future::join_all;
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::sync::watch;
+    use tokio::time::sleep;
+
+    use crate::cache::cache::DiskCache;
+    use crate::config::config::Config;
+
+    use crate::aqi::aqi::AqiSource;
+    use crate::email::email::EmailSource;
+    use crate::location::location::Resolver;
+    use crate::strava::strava::StravaSource;
+    use crate::uv::uv::UvSource;
+    use crate::weather::weather::WeatherSource;
+
+    /// What a source has to show. `Hidden` means it fetched fine but
+    /// has nothing worth showing, like a normal AQI, which is
+    /// different from failing to fetch.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(tag = "state", content = "value", rename_all = "lowercase")]
+    pub enum Reading<T> {
+        Shown(T),
+        Hidden,
+    }
+
+    #[async_trait]
+    pub trait DataSource: Send + Sync {
+        type Output: Serialize + DeserializeOwned + Send;
+        type Error: std::error::Error + Send + Sync + 'static;
+
+        /// The name the source is registered under.
+        const NAME: &'static str;
+
+        /// How long a reading stays fresh before it's fetched again.
+        fn refresh_interval(&self) -> Duration;
+
+        /// How long to wait for a fetch before giving up on it.
+        fn timeout(&self) -> Duration {
+            Duration::seconds(10)
+        }
+
+        /// How old a reading can get before it's shown as stale, which
+        /// by default allows a couple of failed refreshes.
+        fn stale_after(&self) -> Duration {
+            self.refresh_interval() * 3
+        }
+
+        /// How old a cached reading can be and still be shown after a
+        /// restart. Anything older is dropped and fetched from scratch.
+        fn cache_ttl(&self) -> Duration {
+            Duration::days(1)
+        }
+
+        async fn fetch(&self) -> Result<Reading<Self::Output>, Self::Error>;
+    }
+
+    /// A source with its output turned into JSON, so
+    /// that sources of every type can share a registry.
+    #[async_trait]
+    trait AnySource: Send + Sync {
+        fn name(&self) -> &'static str;
+        fn refresh_interval(&self) -> Duration;
+        fn timeout(&self) -> Duration;
+        fn cache_ttl(&self) -> Duration;
+        async fn fetch_value(&self) -> Result<Reading<Value>>;
+        /// Check that a reading is still the source's output type,
+        /// since a cached one may be from an older version.
+        fn check(&self, reading: &Reading<Value>) -> Result<()>;
+    }
+
+    #[async_trait]
+    impl<S: DataSource> AnySource for S {
+        fn name(&self) -> &'static str {
+            S::NAME
+        }
+
+        fn refresh_interval(&self) -> Duration {
+            DataSource::refresh_interval(self)
+        }
+
+        fn timeout(&self) -> Duration {
+            DataSource::timeout(self)
+        }
+
+        fn cache_ttl(&self) -> Duration {
+            DataSource::cache_ttl(self)
+        }
+
+        async fn fetch_value(&self) -> Result<Reading<Value>> {
+            Ok(match self.fetch().await? {
+                Reading::Shown(output) => Reading::Shown(serde_json::to_value(output)?),
+                Reading::Hidden => Reading::Hidden,
+            })
+        }
+
+        fn check(&self, reading: &Reading<Value>) -> Result<()> {
+            if let Reading::Shown(value) = reading {
+                S::Output::deserialize(value)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Everything known about one source.
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct SourceState {
+        /// The last reading that was fetched. A failed fetch
+        /// leaves this alone, so the last good one stays up.
+        pub reading: Option<Reading<Value>>,
+        pub fetched_at: Option<DateTime<Utc>>,
+        /// Why the last fetch failed, if it did.
+        pub error: Option<String>,
+        /// When the last failed fetch was, and how many have
+        /// failed in a row, for backing off.
+        #[serde(default)]
+        pub failed_at: Option<DateTime<Utc>>,
+        #[serde(default)]
+        pub failures: u32,
+    }
+
+    /// How long to wait after the first failed fetch before trying again,
+    /// in seconds. Each failure in a row doubles it, up to the refresh
+    /// interval.
+    const RETRY_DELAY: i64 = 30;
+
+    impl SourceState {
+        /// Whether a source with this state should be fetched at `now`.
+        fn due(&self, now: DateTime<Utc>, refresh_interval: Duration) -> bool {
+            if let (Some(failed_at), Some(doublings)) =
+                (self.failed_at, self.failures.checked_sub(1))
+            {
+                let backoff =
+                    Duration::seconds(RETRY_DELAY << doublings.min(16)).min(refresh_interval);
+                return now - failed_at >= backoff;
+            }
+            self.fetched_at
+                .map(|fetched_at| now - fetched_at >= refresh_interval)
+                .unwrap_or(true)
+        }
+    }
+
+    /// The state of every source, by name.
+    #[derive(Debug, Clone, Default)]
+    pub struct Readings {
+        states: HashMap<String, SourceState>,
+        /// Each registered source's `stale_after`.
+        stale_after: HashMap<String, Duration>,
+    }
+
+    impl Readings {
+        /// What a source has to show, or `None` if it's hidden
+        /// or has never been fetched.
+        pub fn get<S: DataSource>(&self) -> Option<S::Output> {
+            match self.states.get(S::NAME)?.reading.as_ref()? {
+                Reading::Shown(value) => match S::Output::deserialize(value) {
+                    Ok(output) => Some(output),
+                    Err(err) => {
+                        println!("Could not read {}: {}", S::NAME, err);
+                        None
+                    }
+                },
+                Reading::Hidden => None,
+            }
+        }
+
+        /// Whether what a source shows is out of date, so widgets can
+        /// draw it dimmed: either its last fetch failed, or it's older
+        /// than `max_age`, or the source's own limit if that's `None`.
+        pub fn stale(&self, name: &str, now: DateTime<Utc>, max_age: Option<Duration>) -> bool {
+            let Some(state) = self.states.get(name) else {
+                return false;
+            };
+            if state.reading.is_none() {
+                return false;
+            }
+            if state.error.is_some() {
+                return true;
+            }
+            let max_age = max_age.or_else(|| self.stale_after.get(name).copied());
+            match (state.fetched_at, max_age) {
+                (Some(fetched_at), Some(max_age)) => now - fetched_at > max_age,
+                _ => false,
+            }
+        }
+
+        /// Whether a source has only ever failed, so there's
+        /// nothing to show but an error.
+        pub fn failed(&self, name: &str) -> bool {
+            self.states
+                .get(name)
+                .map(|state| state.reading.is_none() && state.error.is_some())
+                .unwrap_or(false)
+        }
+
+        /// Every source's state, by name.
+        pub fn states(&self) -> &HashMap<String, SourceState> {
+            &self.states
+        }
+
+        /// Set a source's reading directly, for sample data.
+        pub fn insert<S: DataSource>(&mut self, reading: Reading<S::Output>) -> Result<()> {
+            let reading = match reading {
+                Reading::Shown(output) => Reading::Shown(serde_json::to_value(output)?),
+                Reading::Hidden => Reading::Hidden,
+            };
+            self.states.insert(
+                String::from(S::NAME),
+                SourceState {
+                    reading: Some(reading),
+                    fetched_at: Some(Utc::now()),
+                    ..SourceState::default()
+                },
+            );
+            Ok(())
+        }
+    }
+
+    /// How often the background refresh checks for sources that are due.
+    const REFRESH_TICK: std::time::Duration = std::time::Duration::from_secs(10);
+
+    /// The registered sources, and what they last returned.
+    pub struct Registry {
+        sources: Vec<Box<dyn AnySource>>,
+        readings: Readings,
+        cache: Option<DiskCache>,
+        /// Sends a copy of the readings after every refresh.
+        sender: watch::Sender<Readings>,
+    }
+
+    impl Default for Registry {
+        fn default() -> Registry {
+            Registry {
+                sources: Vec::new(),
+                readings: Readings::default(),
+                cache: None,
+                sender: watch::channel(Readings::default()).0,
+            }
+        }
+    }
+
+    impl Registry {
+        /// Add a source, replacing any already registered under its name.
+        pub fn register<S: DataSource + 'static>(mut self, source: S) -> Registry {
+            self.sources.retain(|existing| existing.name() != S::NAME);
+            self.readings
+                .stale_after
+                .insert(String::from(S::NAME), source.stale_after());
+            self.sources.push(Box::new(source));
+            self
+        }
+
+        /// Keep readings in `cache`, starting from whatever's already
+        /// there for the sources registered so far. Readings that don't
+        /// fit the source any more are dropped, so it's fetched again.
+        pub fn set_cache(mut self, cache: DiskCache) -> Registry {
+            for source in &self.sources {
+                let Some(state) = cache.load(source.name(), source.cache_ttl()) else {
+                    continue;
+                };
+                if let Some(Err(err)) = state.reading.as_ref().map(|reading| source.check(reading))
+                {
+                    println!("Ignoring cached {}: {:#}", source.name(), err);
+                    continue;
+                }
+                self.readings
+                    .states
+                    .insert(String::from(source.name()), state);
+            }
+            self.cache = Some(cache);
+            self
+        }
+
+        /// Whether every source has something from the cache,
+        /// so there's no need to wait for a first fetch.
+        pub fn warm(&self) -> bool {
+            self.sources
+                .iter()
+                .all(|source| self.readings.states.contains_key(source.name()))
+        }
+
+        /// A receiver that always has the latest readings.
+        pub fn subscribe(&self) -> watch::Receiver<Readings> {
+            self.sender.send_replace(self.readings.clone());
+            self.sender.subscribe()
+        }
+
+        /// Keep refreshing in the background, for as long as
+        /// anything is subscribed.
+        pub async fn run(mut self) {
+            while !self.sender.is_closed() {
+                self.refresh().await;
+                sleep(REFRESH_TICK).await;
+            }
+        }
+
+        /// Fetch every source whose last reading is older than its
+        /// refresh interval, all at once and each with its own timeout.
+        /// Sources that fail are tried again after a delay that grows
+        /// with each failure, and keep their last good reading until
+        /// then, marked as stale.
+        pub async fn refresh(&mut self) {
+            let now = Utc::now();
+            let due = self.sources.iter().filter(|source| {
+                self.readings
+                    .states
+                    .get(source.name())
+                    .map(|state| state.due(now, source.refresh_interval()))
+                    .unwrap_or(true)
+            });
+            let results = join_all(due.map(|source| async move {
+                println!("Fetching {}", source.name());
+                let timeout = source.timeout();
+                let result = tokio::time::timeout(
+                    timeout.to_std().unwrap_or_default(),
+                    source.fetch_value(),
+                )
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("timed out after {}s", timeout.num_seconds())));
+                (source.name(), result)
+            }))
+            .await;
+
+            for (name, result) in results {
+                let state = self.readings.states.entry(String::from(name)).or_default();
+                match result {
+                    Ok(reading) => {
+                        state.reading = Some(reading);
+                        state.fetched_at = Some(now);
+                        state.error = None;
+                        state.failed_at = None;
+                        state.failures = 0;
+                    }
+                    Err(err) => {
+                        state.error = Some(format!("{:#}", err));
+                        state.failed_at = Some(now);
+                        state.failures = state.failures.saturating_add(1);
+                        println!(
+                            "Could not fetch {} ({} in a row): {:#}",
+                            name, state.failures, err
+                        );
+                    }
+                }
+                if let Some(cache) = &self.cache {
+                    if let Err(err) = cache.store(name, state) {
+                        println!("Could not cache {}: {:#}", name, err);
+                    }
+                }
+            }
+            self.sender.send_replace(self.readings.clone());
+        }
+    }
+
+    /// Every source the display knows about.
+    pub fn default_registry(resolver: &Arc<Resolver>, config: &Config) -> Registry {
+        Registry::default()
+            .register(WeatherSource::new(resolver.clone()))
+            .register(AqiSource {
+                resolver: resolver.clone(),
+                always_show: config.aqi.always_show,
+            })
+            .register(UvSource {
+                resolver: resolver.clone(),
+            })
+            .register(EmailSource)
+            .register(StravaSource)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::TimeZone;
+        use tempfile::TempDir;
+
+        #[test]
+        fn failed_fetches_back_off_up_to_the_refresh_interval() {
+            let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+            let hour = Duration::hours(1);
+            let failed = |failures: u32, ago: i64| SourceState {
+                failed_at: Some(now - Duration::seconds(ago)),
+                failures,
+                ..SourceState::default()
+            };
+            assert!(SourceState::default().due(now, hour));
+            assert!(!failed(1, 29).due(now, hour));
+            assert!(failed(1, 30).due(now, hour));
+            assert!(!failed(3, 119).due(now, hour));
+            assert!(failed(3, 120).due(now, hour));
+            assert!(!failed(20, 3599).due(now, hour));
+            assert!(failed(20, 3600).due(now, hour));
+        }
+
+        #[test]
+        fn successful_fetches_wait_for_the_refresh_interval() {
+            let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+            let state = SourceState {
+                fetched_at: Some(now - Duration::minutes(59)),
+                ..SourceState::default()
+            };
+            assert!(!state.due(now, Duration::hours(1)));
+            assert!(state.due(now + Duration::minutes(1), Duration::hours(1)));
+        }
+
+        #[derive(Serialize, Deserialize)]
+        struct Distance {
+            meters: f64,
+        }
+
+        struct DistanceSource;
+
+        #[async_trait]
+        impl DataSource for DistanceSource {
+            type Output = Distance;
+            type Error = std::io::Error;
+
+            const NAME: &'static str = "distance";
+
+            fn refresh_interval(&self) -> Duration {
+                Duration::hours(1)
+            }
+
+            async fn fetch(&self) -> Result<Reading<Distance>, std::io::Error> {
+                Ok(Reading::Shown(Distance { meters: 1.0 }))
+            }
+        }
+
+        // The cache, and the directory it's in, which is
+        // removed when the test drops it.
+        fn cached(value: Value) -> (TempDir, DiskCache) {
+            let dir = TempDir::new().unwrap();
+            let cache = DiskCache::new(dir.path()).unwrap();
+            let state = SourceState {
+                reading: Some(Reading::Shown(value)),
+                fetched_at: Some(Utc::now()),
+                ..SourceState::default()
+            };
+            cache.store(DistanceSource::NAME, &state).unwrap();
+            (dir, cache)
+        }
+
+        #[test]
+        fn cached_readings_that_no_longer_fit_are_dropped() {
+            let (_dir, cache) = cached(serde_json::json!({ "miles": 1.0 }));
+            let registry = Registry::default()
+                .register(DistanceSource)
+                .set_cache(cache);
+            assert!(!registry.warm());
+            assert!(registry.readings.get::<DistanceSource>().is_none());
+        }
+
+        #[test]
+        fn cached_readings_that_fit_are_kept() {
+            let (_dir, cache) = cached(serde_json::json!({ "meters": 2.0 }));
+            let registry = Registry::default()
+                .register(DistanceSource)
+                .set_cache(cache);
+            assert!(registry.warm());
+            let distance = registry.readings.get::<DistanceSource>().unwrap();
+            assert_eq!(distance.meters, 2.0);
+        }
+    }
+}
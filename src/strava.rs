@@ -1,16 +1,22 @@
 pub mod strava {
-    use anyhow::{Context, Result};
-    use cached::proc_macro::cached;
-    use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+    use async_trait::async_trait;
+    use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Utc};
     use reqwest::header::USER_AGENT;
     use serde::{Deserialize, Serialize};
+    use thiserror::Error;
     extern crate uom;
     use std::collections::{BTreeMap, HashMap};
     use std::path::PathBuf;
     use std::{env, fs};
-    use uom::si::f32::*;
+    use uom::si::f64::Length;
     use uom::si::length::{meter, mile};
 
+    use crate::chart::chart::{Chart, Ramp, Range, Series, Stop};
+    use crate::font::font::FontName;
+    use crate::locale::locale::Units;
+    use crate::source::source::{DataSource, Reading};
+    use crate::TextWidget;
+
     #[derive(Debug, Serialize, Deserialize)]
     struct TokenResponse {
         token_type: String,
@@ -95,18 +101,106 @@ pub mod strava {
 
     type Activities = Vec<Activity>;
 
-    fn get_token_file_path() -> Result<PathBuf> {
+    /// This week's runs, in meters.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Runs {
+        /// Run today, from the week's bucket for today's date,
+        /// or `None` if there's no bucket for it.
+        pub today_meters: Option<f64>,
+        pub week_meters: f64,
+        /// Each day of the week so far, for a chart.
+        pub day_meters: Vec<f64>,
+    }
+
+    /// Days of this many miles or more top out the chart.
+    const CHART_TOP_MILES: f64 = 7.0;
+
+    impl Runs {
+        pub fn today_text(&self, units: Units) -> TextWidget {
+            match self.today_meters {
+                None => TextWidget {
+                    text: String::from("RUN"),
+                    color: String::from("text"),
+                    font: FontName::default(),
+                    stale: false,
+                },
+                Some(meters) => TextWidget {
+                    text: format!("{:.0} {}", units.distance(meters), units.distance_suffix()),
+                    color: String::from("good"),
+                    font: FontName::default(),
+                    stale: false,
+                },
+            }
+        }
+
+        pub fn week_text(&self, units: Units) -> TextWidget {
+            TextWidget {
+                text: format!("{:.0} WK", units.distance(self.week_meters)),
+                color: String::from("text"),
+                font: FontName::default(),
+                stale: false,
+            }
+        }
+
+        /// A bar for each day, rounded to whole miles or kilometers,
+        /// with rest days muted and long runs topping out in the accent.
+        pub fn chart(&self, units: Units) -> Chart {
+            let values = self
+                .day_meters
+                .iter()
+                .map(|meters| units.distance(*meters).round())
+                .collect();
+            let top = units
+                .distance(Length::new::<mile>(CHART_TOP_MILES).get::<meter>())
+                .round();
+            let stop = |at: f64, color: &str| Stop {
+                at,
+                color: String::from(color),
+            };
+            Chart::new(
+                Series::new(values)
+                    .set_range(Range {
+                        min: Some(0.0),
+                        max: Some(top),
+                    })
+                    .set_ramp(Ramp {
+                        stops: vec![stop(0.0, "muted"), stop(1.0, "text"), stop(top, "accent")],
+                        smooth: false,
+                    }),
+            )
+        }
+    }
+
+    #[derive(Debug, Error)]
+    pub enum StravaError {
+        #[error("{0} is not set")]
+        MissingCredential(&'static str),
+        #[error("could not find the home directory for .strava_token.json")]
+        NoHome,
+        #[error("could not read .strava_token.json: {0}")]
+        Io(#[from] std::io::Error),
+        #[error("could not read the Strava token: {0}")]
+        Json(#[from] serde_json::Error),
+        #[error("could not get activities from Strava: {0}")]
+        Request(#[from] reqwest::Error),
+    }
+
+    fn get_token_file_path() -> Result<PathBuf, StravaError> {
         dirs::home_dir()
             .map(|mut path| {
                 path.push(".strava_token.json");
                 path
             })
-            .context(".strava_token could not be found")
+            .ok_or(StravaError::NoHome)
     }
 
-    async fn fresh_token() -> Result<TokenResponse> {
-        let client_id = env::var("STRAVA_CLIENT_ID").expect("Missing STRAVA_CLIENT_ID");
-        let client_secret = env::var("STRAVA_CLIENT_SECRET").expect("Missing STRAVA_CLIENT_SECRET");
+    fn credential(name: &'static str) -> Result<String, StravaError> {
+        env::var(name).map_err(|_| StravaError::MissingCredential(name))
+    }
+
+    async fn fresh_token() -> Result<TokenResponse, StravaError> {
+        let client_id = credential("STRAVA_CLIENT_ID")?;
+        let client_secret = credential("STRAVA_CLIENT_SECRET")?;
 
         let path = get_token_file_path()?;
         let file_content = fs::read_to_string(path)?;
@@ -136,7 +230,7 @@ pub mod strava {
 
     #[derive(Debug, Clone)]
     struct TimeBucket {
-        miles: f64,
+        meters: f64,
     }
 
     fn month_days() -> BTreeMap<NaiveDate, TimeBucket> {
@@ -148,54 +242,148 @@ pub mod strava {
         bins
     }
 
-    fn week_days() -> BTreeMap<NaiveDate, TimeBucket> {
-        let today = Utc::now().date_naive();
+    /// A bucket for each day from Monday through `today`.
+    fn week_days(today: NaiveDate) -> BTreeMap<NaiveDate, TimeBucket> {
         let days_since_monday = today.weekday().num_days_from_monday();
 
         let mut bins: BTreeMap<NaiveDate, TimeBucket> = BTreeMap::new();
-        for i in 0..days_since_monday {
-            bins.insert(today - Duration::days(i as i64), TimeBucket { miles: 0.0 });
+        for i in 0..=days_since_monday {
+            bins.insert(today - Duration::days(i as i64), TimeBucket { meters: 0.0 });
         }
         bins
     }
 
-    /// Get the number of unread threads in my inbox
-    #[cached(time = 120, result = true)]
-    pub async fn get_strava() -> Result<(Option<f64>, f64, Vec<u64>)> {
-        let tokens = fresh_token().await?;
-        let resp: Activities = reqwest::Client::new()
-            .get("https://www.strava.com/api/v3/athlete/activities")
-            .header(USER_AGENT, "tidbyt")
-            .bearer_auth(tokens.access_token)
-            .send()
-            .await?
-            .json::<Activities>()
-            .await?;
+    /// Add up this week's runs, by the day they started on in `now`'s time zone.
+    fn tally<Tz: TimeZone>(activities: &[Activity], now: &DateTime<Tz>) -> Runs {
+        let today = now.date_naive();
+        let mut bins = week_days(today);
 
-        let mut bins = week_days();
+        let mut week_meters = 0.0;
 
-        let mut miles_total = 0.0;
-
-        for run in resp {
+        for run in activities {
             if run.sport_type == SportType::Run {
-                let length_in_miles = Length::new::<meter>(run.distance).get::<mile>() as f64;
-                let local_start = run.start_date.with_timezone(&Local).date_naive();
+                let local_start = run.start_date.with_timezone(&now.timezone()).date_naive();
 
                 if let Some(bucket) = bins.get_mut(&local_start) {
-                    bucket.miles += length_in_miles;
-                    miles_total += length_in_miles;
+                    bucket.meters += run.distance as f64;
+                    week_meters += run.distance as f64;
                 }
             }
         }
 
-        let mut mi: Vec<u64> = Vec::new();
+        let day_meters = bins.values().map(|bucket| bucket.meters).collect();
+
+        let today_meters = bins.get(&today).map(|bucket| bucket.meters);
 
-        for val in bins.values() {
-            mi.push(val.miles.round() as u64);
+        Runs {
+            today_meters,
+            week_meters,
+            day_meters,
+        }
+    }
+
+    /// Runs from Strava, refreshing the OAuth token in
+    /// ~/.strava_token.json each time.
+    pub struct StravaSource;
+
+    #[async_trait]
+    impl DataSource for StravaSource {
+        type Output = Runs;
+        type Error = StravaError;
+
+        const NAME: &'static str = "strava";
+
+        fn refresh_interval(&self) -> Duration {
+            Duration::minutes(2)
+        }
+
+        // Connecting takes a few round trips, so allow longer.
+        fn timeout(&self) -> Duration {
+            Duration::seconds(20)
+        }
+
+        async fn fetch(&self) -> Result<Reading<Runs>, StravaError> {
+            let tokens = fresh_token().await?;
+            let resp: Activities = reqwest::Client::new()
+                .get("https://www.strava.com/api/v3/athlete/activities")
+                .header(USER_AGENT, "tidbyt")
+                .bearer_auth(tokens.access_token)
+                .send()
+                .await?
+                .json::<Activities>()
+                .await?;
+
+            Ok(Reading::Shown(tally(&resp, &Local::now())))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::FixedOffset;
+
+        fn run(start: &str, distance: f32) -> Activity {
+            Activity {
+                distance,
+                sport_type: SportType::Run,
+                start_date: start.parse().unwrap(),
+            }
         }
 
-        let run_today = bins.first_entry().map(|entry| entry.get().miles);
+        #[test]
+        fn today_is_todays_bucket_not_mondays() {
+            // Sunday, after runs on Monday and Wednesday and one this morning.
+            let now = Utc.with_ymd_and_hms(2026, 10, 18, 15, 0, 0).unwrap();
+            let activities = [
+                run("2026-10-12T11:00:00Z", 5000.0),
+                run("2026-10-14T11:00:00Z", 8000.0),
+                run("2026-10-18T11:00:00Z", 3000.0),
+            ];
+            let runs = tally(&activities, &now);
+            assert_eq!(runs.today_meters, Some(3000.0));
+            assert_eq!(runs.week_meters, 16000.0);
+            assert_eq!(
+                runs.day_meters,
+                [5000.0, 0.0, 8000.0, 0.0, 0.0, 0.0, 3000.0]
+            );
+        }
 
-        Ok((run_today, miles_total, mi))
+        #[test]
+        fn nothing_run_today_is_zero() {
+            let now = Utc.with_ymd_and_hms(2026, 10, 18, 15, 0, 0).unwrap();
+            let runs = tally(&[run("2026-10-12T11:00:00Z", 5000.0)], &now);
+            assert_eq!(runs.today_meters, Some(0.0));
+            assert_eq!(runs.week_meters, 5000.0);
+        }
+
+        #[test]
+        fn mondays_count_their_own_runs() {
+            let now = Utc.with_ymd_and_hms(2026, 10, 19, 15, 0, 0).unwrap();
+            let activities = [
+                run("2026-10-18T11:00:00Z", 3000.0),
+                run("2026-10-19T11:00:00Z", 4000.0),
+            ];
+            let runs = tally(&activities, &now);
+            assert_eq!(runs.today_meters, Some(4000.0));
+            assert_eq!(runs.week_meters, 4000.0);
+            assert_eq!(runs.day_meters, [4000.0]);
+        }
+
+        #[test]
+        fn runs_go_by_the_day_they_started_locally() {
+            // 9pm on Saturday in New York is already Sunday in UTC.
+            let new_york = FixedOffset::west_opt(4 * 3600).unwrap();
+            let now = new_york.with_ymd_and_hms(2026, 10, 17, 22, 0, 0).unwrap();
+            let activities = [
+                run("2026-10-18T01:00:00Z", 6000.0),
+                Activity {
+                    sport_type: SportType::Ride,
+                    ..run("2026-10-18T00:00:00Z", 20000.0)
+                },
+            ];
+            let runs = tally(&activities, &now);
+            assert_eq!(runs.today_meters, Some(6000.0));
+            assert_eq!(runs.day_meters.len(), 6);
+        }
     }
 }
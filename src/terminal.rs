@@ -0,0 +1,95 @@
+/// Drawing rendered frames in a truecolor terminal, two pixels
+/// to a character cell, so layouts can be checked over SSH.
+pub mod terminal {
+    use crate::animation::animation::Frame;
+    use std::fmt::Write as _;
+    use std::io::Write as _;
+    use tokio::sync::watch;
+    use tokio::time::{sleep, Duration};
+
+    /// The top pixel is the character, and the bottom one the background.
+    const UPPER_HALF: char = '▀';
+
+    /// A pixel's color over black, since the display has no transparency.
+    fn pixel(frame: &Frame, width: u32, x: u32, y: u32) -> (u8, u8, u8) {
+        let i = ((y * width + x) * 4) as usize;
+        let Some([r, g, b, a]) = frame.rgba.get(i..i + 4) else {
+            return (0, 0, 0);
+        };
+        let over_black = |channel: u8| (channel as u32 * *a as u32 / 255) as u8;
+        (over_black(*r), over_black(*g), over_black(*b))
+    }
+
+    /// The frame as rows of half blocks, each ending in a newline.
+    pub fn draw(frame: &Frame, width: u32, height: u32) -> String {
+        let mut out = String::new();
+        for y in (0..height).step_by(2) {
+            for x in 0..width {
+                let (r, g, b) = pixel(frame, width, x, y);
+                let (br, bg, bb) = match y + 1 < height {
+                    true => pixel(frame, width, x, y + 1),
+                    false => (0, 0, 0),
+                };
+                let _ = write!(
+                    out,
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m{}",
+                    r, g, b, br, bg, bb, UPPER_HALF
+                );
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+
+    /// Plays the latest frames at the top of the terminal, looping
+    /// until new ones arrive. Everything else that's printed scrolls
+    /// by underneath.
+    pub struct TerminalPreview {
+        sender: watch::Sender<Vec<Frame>>,
+    }
+
+    impl TerminalPreview {
+        /// Clear the terminal and start playing, which has to
+        /// happen inside the Tokio runtime.
+        pub fn new(width: u32, height: u32) -> TerminalPreview {
+            let (sender, receiver) = watch::channel(Vec::new());
+            let rows = height.div_ceil(2);
+            // Keep the top rows for the image by scrolling only the
+            // ones below it, and put that back when we're stopped.
+            print!("\x1b[2J\x1b[{}r\x1b[{};1H", rows + 2, rows + 2);
+            tokio::spawn(async {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    print!("\x1b[r\x1b[0m");
+                    std::process::exit(0);
+                }
+            });
+            tokio::spawn(play(receiver, width, height));
+            TerminalPreview { sender }
+        }
+
+        pub fn show(&self, frames: &[Frame]) {
+            self.sender.send_replace(frames.to_vec());
+        }
+    }
+
+    async fn play(mut receiver: watch::Receiver<Vec<Frame>>, width: u32, height: u32) {
+        loop {
+            let frames = receiver.borrow_and_update().clone();
+            for frame in frames.iter().cycle() {
+                // Save the cursor, draw from the top left, and put it back.
+                print!("\x1b7\x1b[1;1H{}\x1b8", draw(frame, width, height));
+                let _ = std::io::stdout().flush();
+                tokio::select! {
+                    _ = sleep(Duration::from_millis(frame.duration.max(1) as u64)) => {}
+                    changed = receiver.changed() => match changed {
+                        Ok(()) => break,
+                        Err(_) => return,
+                    },
+                }
+            }
+            if frames.is_empty() && receiver.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
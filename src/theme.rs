@@ -0,0 +1,265 @@
+/// Named colors, so widgets and layouts can say what a color is
+/// for, and the config can choose how that looks.
+pub mod theme {
+    use anyhow::{anyhow, Result};
+    use once_cell::sync::OnceCell;
+    use palette::Srgb;
+    use serde::Deserialize;
+    use std::str::FromStr;
+
+    /// A built-in set of colors to start from.
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum Palette {
+        #[default]
+        Default,
+        /// Fully saturated colors and brighter grays, for
+        /// reading from across a room.
+        HighContrast,
+        /// From the Okabe-Ito palette, which stays distinct with
+        /// the common kinds of color blindness.
+        Colorblind,
+        /// Everything in shades of amber.
+        Amber,
+    }
+
+    /// The color for each name, as hex.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Theme {
+        /// Readings that are fine, like a low UV index.
+        pub good: String,
+        /// Readings worth noticing.
+        pub warn: String,
+        /// Readings to act on, and errors.
+        pub bad: String,
+        /// Highlights, like a chart's best values.
+        pub accent: String,
+        /// Things in the background, like empty bars and goal lines.
+        pub muted: String,
+        pub text: String,
+        /// Highs, and temperatures above freezing.
+        pub warm: String,
+        /// Lows, and temperatures at or below freezing.
+        pub cold: String,
+        /// Rain and snow, and the chance of them.
+        pub precip: String,
+        /// The weather icons' colors, named `icon.sun` and so on.
+        pub icon_sun: String,
+        pub icon_rays: String,
+        pub icon_moon: String,
+        pub icon_cloud: String,
+        /// Dark clouds, for storms.
+        pub icon_storm: String,
+        pub icon_snow: String,
+    }
+
+    /// Every name in a theme.
+    const NAMES: [&str; 15] = [
+        "good",
+        "warn",
+        "bad",
+        "accent",
+        "muted",
+        "text",
+        "warm",
+        "cold",
+        "precip",
+        "icon.sun",
+        "icon.rays",
+        "icon.moon",
+        "icon.cloud",
+        "icon.storm",
+        "icon.snow",
+    ];
+
+    impl Palette {
+        pub fn theme(&self) -> Theme {
+            let [good, warn, bad, accent, muted, text] = match self {
+                Palette::Default => ["#92dd67", "#ffb537", "#ff3838", "#0ff", "#555", "#fff"],
+                Palette::HighContrast => ["#0f0", "#ff0", "#f00", "#0ff", "#888", "#fff"],
+                Palette::Colorblind => ["#56b4e9", "#f0e442", "#d55e00", "#cc79a7", "#555", "#fff"],
+                Palette::Amber => ["#fd8", "#fa0", "#f50", "#fc0", "#640", "#fb4"],
+            };
+            let [warm, cold, precip] = match self {
+                Palette::Default => ["#f96", "#6cf", "#39f"],
+                Palette::HighContrast => ["#f80", "#0ff", "#08f"],
+                Palette::Colorblind => ["#e69f00", "#56b4e9", "#0072b2"],
+                Palette::Amber => ["#fc6", "#c80", "#a60"],
+            };
+            let [sun, rays, moon, cloud, storm, snow] = match self {
+                Palette::Default => ["#fc0", "#f80", "#ffc", "#ddd", "#888", "#fff"],
+                Palette::HighContrast => ["#ff0", "#f80", "#ffc", "#fff", "#aaa", "#fff"],
+                Palette::Colorblind => ["#f0e442", "#e69f00", "#ffc", "#ddd", "#888", "#fff"],
+                Palette::Amber => ["#fc0", "#fa0", "#fd8", "#c80", "#640", "#fd8"],
+            };
+            Theme {
+                good: String::from(good),
+                warn: String::from(warn),
+                bad: String::from(bad),
+                accent: String::from(accent),
+                muted: String::from(muted),
+                text: String::from(text),
+                warm: String::from(warm),
+                cold: String::from(cold),
+                precip: String::from(precip),
+                icon_sun: String::from(sun),
+                icon_rays: String::from(rays),
+                icon_moon: String::from(moon),
+                icon_cloud: String::from(cloud),
+                icon_storm: String::from(storm),
+                icon_snow: String::from(snow),
+            }
+        }
+    }
+
+    impl Default for Theme {
+        fn default() -> Theme {
+            Palette::default().theme()
+        }
+    }
+
+    impl Theme {
+        /// The hex for a name, if it's one of the theme's.
+        pub fn get(&self, name: &str) -> Option<&str> {
+            Some(match name {
+                "good" => &self.good,
+                "warn" => &self.warn,
+                "bad" => &self.bad,
+                "accent" => &self.accent,
+                "muted" => &self.muted,
+                "text" => &self.text,
+                "warm" => &self.warm,
+                "cold" => &self.cold,
+                "precip" => &self.precip,
+                "icon.sun" => &self.icon_sun,
+                "icon.rays" => &self.icon_rays,
+                "icon.moon" => &self.icon_moon,
+                "icon.cloud" => &self.icon_cloud,
+                "icon.storm" => &self.icon_storm,
+                "icon.snow" => &self.icon_snow,
+                _ => return None,
+            })
+        }
+    }
+
+    /// The `theme` section of the config: a palette, with
+    /// any of its colors replaced.
+    #[derive(Clone, Debug, Default, Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    pub struct ThemeConfig {
+        pub palette: Palette,
+        pub good: Option<String>,
+        pub warn: Option<String>,
+        pub bad: Option<String>,
+        pub accent: Option<String>,
+        pub muted: Option<String>,
+        pub text: Option<String>,
+        pub warm: Option<String>,
+        pub cold: Option<String>,
+        pub precip: Option<String>,
+        #[serde(rename = "icon.sun")]
+        pub icon_sun: Option<String>,
+        #[serde(rename = "icon.rays")]
+        pub icon_rays: Option<String>,
+        #[serde(rename = "icon.moon")]
+        pub icon_moon: Option<String>,
+        #[serde(rename = "icon.cloud")]
+        pub icon_cloud: Option<String>,
+        #[serde(rename = "icon.storm")]
+        pub icon_storm: Option<String>,
+        #[serde(rename = "icon.snow")]
+        pub icon_snow: Option<String>,
+    }
+
+    impl ThemeConfig {
+        /// The palette with the replacements, which have to be hex.
+        pub fn theme(&self) -> Result<Theme> {
+            let mut theme = self.palette.theme();
+            for (name, color, replacement) in [
+                ("good", &mut theme.good, &self.good),
+                ("warn", &mut theme.warn, &self.warn),
+                ("bad", &mut theme.bad, &self.bad),
+                ("accent", &mut theme.accent, &self.accent),
+                ("muted", &mut theme.muted, &self.muted),
+                ("text", &mut theme.text, &self.text),
+                ("warm", &mut theme.warm, &self.warm),
+                ("cold", &mut theme.cold, &self.cold),
+                ("precip", &mut theme.precip, &self.precip),
+                ("icon.sun", &mut theme.icon_sun, &self.icon_sun),
+                ("icon.rays", &mut theme.icon_rays, &self.icon_rays),
+                ("icon.moon", &mut theme.icon_moon, &self.icon_moon),
+                ("icon.cloud", &mut theme.icon_cloud, &self.icon_cloud),
+                ("icon.storm", &mut theme.icon_storm, &self.icon_storm),
+                ("icon.snow", &mut theme.icon_snow, &self.icon_snow),
+            ] {
+                if let Some(replacement) = replacement {
+                    Srgb::<u8>::from_str(replacement)
+                        .map_err(|_| anyhow!("{} is not a hex color: `{}`", name, replacement))?;
+                    *color = replacement.clone();
+                }
+            }
+            Ok(theme)
+        }
+    }
+
+    static THEME: OnceCell<Theme> = OnceCell::new();
+
+    /// Set the theme that color names resolve through.
+    pub fn set_theme(theme: Theme) {
+        let _ = THEME.set(theme);
+    }
+
+    pub fn theme() -> &'static Theme {
+        THEME.get_or_init(Theme::default)
+    }
+
+    /// A color given as hex, like `#fc0`, or by a name in the theme.
+    pub fn resolve(color: &str) -> Result<Srgb<u8>> {
+        let hex = match color.starts_with('#') {
+            true => color,
+            false => theme().get(color).ok_or_else(|| {
+                anyhow!(
+                    "invalid color `{}`, which should be hex or one of {}",
+                    color,
+                    NAMES.join(", ")
+                )
+            })?,
+        };
+        Srgb::from_str(hex).map_err(|_| anyhow!("invalid color `{}`", color))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn every_name_is_a_color_in_every_palette() {
+            for palette in [
+                Palette::Default,
+                Palette::HighContrast,
+                Palette::Colorblind,
+                Palette::Amber,
+            ] {
+                let theme = palette.theme();
+                for name in NAMES {
+                    let hex = theme.get(name).unwrap();
+                    assert!(Srgb::<u8>::from_str(hex).is_ok(), "{:?} {}", palette, name);
+                }
+            }
+        }
+
+        #[test]
+        fn the_config_replaces_names() {
+            let config: ThemeConfig = serde_json::from_str(
+                r##"{"palette": "amber", "precip": "#00f", "icon.sun": "#ff0"}"##,
+            )
+            .unwrap();
+            let theme = config.theme().unwrap();
+            assert_eq!(theme.get("precip"), Some("#00f"));
+            assert_eq!(theme.get("icon.sun"), Some("#ff0"));
+            assert_eq!(theme.get("warm"), Some("#fc6"));
+            let config: ThemeConfig = serde_json::from_str(r#"{"cold": "blue"}"#).unwrap();
+            assert!(config.theme().is_err());
+        }
+    }
+}
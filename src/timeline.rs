@@ -0,0 +1,224 @@
+/// Merging the frames of several animated widgets into one timeline.
+pub mod timeline {
+    use anyhow::{anyhow, Result};
+    use serde::Deserialize;
+    use std::collections::BTreeSet;
+
+    /// How long a frame is shown when a widget doesn't say otherwise, in ms.
+    pub const DEFAULT_FRAME_DURATION: u32 = 100;
+
+    /// Loops longer than this fall back to the longest child's loop,
+    /// so that children with awkward lengths don't make huge files.
+    const MAX_LOOP_DURATION: u32 = 60_000;
+
+    /// How a stack lines up the animations of its children.
+    #[derive(Debug, Clone, Default, Deserialize)]
+    #[serde(tag = "mode", rename_all = "lowercase", deny_unknown_fields)]
+    pub enum Schedule {
+        /// Loop for the least common multiple of the children's loops,
+        /// with a new frame whenever any child changes.
+        #[default]
+        Lcm,
+        /// Exactly `frames` frames of `duration` ms each, showing
+        /// whichever frame each child is on at that time.
+        Explicit { frames: u32, duration: u32 },
+    }
+
+    impl Schedule {
+        /// Check that an explicit schedule has frames and fits in a loop.
+        pub fn validate(&self) -> Result<()> {
+            let Schedule::Explicit { frames, duration } = self else {
+                return Ok(());
+            };
+            if *frames == 0 || *duration == 0 {
+                return Err(anyhow!(
+                    "explicit schedule needs at least one frame of at least 1ms"
+                ));
+            }
+            match frames.checked_mul(*duration) {
+                Some(total) if total <= MAX_LOOP_DURATION => Ok(()),
+                _ => Err(anyhow!(
+                    "explicit schedule of {} frames of {}ms is longer than {}ms",
+                    frames,
+                    duration,
+                    MAX_LOOP_DURATION
+                )),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct Frame {
+        duration: u32,
+        children: Vec<u32>,
+    }
+
+    /// The frames of a stack, each with a duration and the
+    /// frame that each child should render during it.
+    #[derive(Debug)]
+    pub struct Timeline {
+        frames: Vec<Frame>,
+    }
+
+    fn gcd(a: u32, b: u32) -> u32 {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+
+    fn lcm(a: u32, b: u32) -> Option<u32> {
+        (a / gcd(a, b)).checked_mul(b)
+    }
+
+    /// The frame of a track showing at `time`, looping the track.
+    fn frame_at(track: &[u32], time: u32) -> u32 {
+        let total = total(track);
+        if track.len() < 2 || total == 0 {
+            return 0;
+        }
+        let mut time = time % total;
+        for (i, duration) in track.iter().enumerate() {
+            if time < *duration {
+                return i as u32;
+            }
+            time -= duration;
+        }
+        0
+    }
+
+    /// A track's length, which saturates rather than overflowing.
+    fn total(track: &[u32]) -> u32 {
+        track
+            .iter()
+            .fold(0, |sum, duration| sum.saturating_add(*duration))
+    }
+
+    /// The least common multiple of the loops' lengths, or the
+    /// longest loop if that would be too long.
+    fn loop_length(animated: &[&Vec<u32>]) -> u32 {
+        let totals: Vec<u32> = animated.iter().map(|t| total(t)).collect();
+        let longest = totals.iter().copied().max().unwrap_or(0);
+        totals
+            .iter()
+            .try_fold(1, |acc, total| lcm(acc, *total))
+            .filter(|length| *length <= MAX_LOOP_DURATION)
+            .unwrap_or(longest)
+    }
+
+    impl Timeline {
+        /// Build a timeline from each child's frame durations.
+        pub fn new(tracks: &[Vec<u32>], schedule: &Schedule) -> Timeline {
+            let animated: Vec<&Vec<u32>> = tracks
+                .iter()
+                .filter(|track| track.len() > 1 && total(track) > 0)
+                .collect();
+
+            let (starts, end): (Vec<u32>, u32) = match schedule {
+                Schedule::Explicit { frames, duration } => {
+                    // Layouts are validated, but saturate rather
+                    // than overflow if one somehow isn't.
+                    let frames = (*frames).max(1);
+                    (
+                        (0..frames).map(|i| i.saturating_mul(*duration)).collect(),
+                        frames.saturating_mul(*duration),
+                    )
+                }
+                Schedule::Lcm if animated.is_empty() => {
+                    // Nothing moves, so one frame is enough.
+                    let duration = tracks
+                        .iter()
+                        .filter_map(|track| track.first())
+                        .copied()
+                        .max()
+                        .unwrap_or(DEFAULT_FRAME_DURATION);
+                    (vec![0], duration)
+                }
+                Schedule::Lcm => {
+                    let length = loop_length(&animated);
+                    let mut starts = BTreeSet::new();
+                    for track in &animated {
+                        let mut time = 0;
+                        'track: loop {
+                            for duration in track.iter() {
+                                if time >= length {
+                                    break 'track;
+                                }
+                                starts.insert(time);
+                                time = time.saturating_add(*duration);
+                            }
+                        }
+                    }
+                    (starts.into_iter().collect(), length)
+                }
+            };
+
+            let frames = starts
+                .iter()
+                .enumerate()
+                .map(|(i, start)| Frame {
+                    duration: starts.get(i + 1).copied().unwrap_or(end) - start,
+                    children: tracks.iter().map(|track| frame_at(track, *start)).collect(),
+                })
+                .collect();
+            Timeline { frames }
+        }
+
+        pub fn frame_count(&self) -> u32 {
+            self.frames.len() as u32
+        }
+
+        pub fn duration(&self, frame: u32) -> u32 {
+            self.frames
+                .get(frame as usize)
+                .map(|f| f.duration)
+                .unwrap_or(DEFAULT_FRAME_DURATION)
+        }
+
+        /// The frame that child `child` should render during `frame`.
+        pub fn child_frame(&self, frame: u32, child: usize) -> u32 {
+            self.frames
+                .get(frame as usize)
+                .and_then(|f| f.children.get(child))
+                .copied()
+                .unwrap_or(0)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn explicit_schedules_that_overflow_are_rejected() {
+            let schedule = Schedule::Explicit {
+                frames: 100_000,
+                duration: 100_000,
+            };
+            assert!(schedule.validate().is_err());
+            let schedule = Schedule::Explicit {
+                frames: 601,
+                duration: 100,
+            };
+            assert!(schedule.validate().is_err());
+            let schedule = Schedule::Explicit {
+                frames: 600,
+                duration: 100,
+            };
+            assert!(schedule.validate().is_ok());
+        }
+
+        #[test]
+        fn unvalidated_explicit_schedules_saturate() {
+            let schedule = Schedule::Explicit {
+                frames: 3,
+                duration: u32::MAX / 2,
+            };
+            let timeline = Timeline::new(&[vec![100, 100]], &schedule);
+            assert_eq!(timeline.frame_count(), 3);
+            assert_eq!(timeline.duration(0), u32::MAX / 2);
+            assert_eq!(timeline.duration(2), 1);
+        }
+    }
+}
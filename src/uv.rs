@@ -1,9 +1,16 @@
 pub mod uv {
-    use anyhow::{anyhow, Context, Result};
-    use cached::proc_macro::cached;
+    use async_trait::async_trait;
+    use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+    use chrono_tz::Tz;
     use reqwest::header::USER_AGENT;
     use serde::{Deserialize, Serialize};
+    use std::sync::Arc;
+    use thiserror::Error;
 
+    use crate::chart::chart::{Chart, ChartKind, Ramp, Range, Series, Stop};
+    use crate::font::font::FontName;
+    use crate::location::location::{LocationError, Resolver};
+    use crate::source::source::{DataSource, Reading};
     use crate::TextWidget;
 
     #[derive(Debug, Serialize, Deserialize)]
@@ -16,37 +23,306 @@ pub mod uv {
         UV_VALUE: i32,
     }
 
-    /// Get the peak UV value today
-    #[cached(time = 3600, result = true)]
-    pub async fn get_uv() -> Result<TextWidget> {
-        // https://www.epa.gov/enviro/web-services#uvindex
-        let resp: Vec<Data> = reqwest::Client::new()
-            .get("https://data.epa.gov/efservice/getEnvirofactsUVHOURLY/ZIP/11201/json")
-            .header(USER_AGENT, "tidbyt")
-            .send()
-            .await?
-            .json::<Vec<Data>>()
-            .await?;
-
-        let uv = resp
-            .iter()
-            .map(|d| d.UV_VALUE)
-            .max()
-            .context("Could not get max UV")?;
-
-        let uv_color = match uv {
-            0..=4 => "#92dd67",
-            5..=9 => "#ffb537",
-            _ => "#ff3838",
-        };
-
-        if uv < 5 {
-            Err(anyhow!("UV is fine"))
-        } else {
-            Ok(TextWidget {
-                text: format!("{} UV", uv),
+    /// The peak below which the `uv` text is hidden, since
+    /// there's nothing worth mentioning.
+    const TEXT_FROM: i32 = 5;
+
+    /// The top of a chart's scale. The EPA's scale is open-ended, but
+    /// 11 and up are all extreme, and this leaves the top row of an
+    /// eight pixel chart for the marks.
+    const CHART_MAX: f64 = 12.0;
+
+    /// One hour of the forecast.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct UvHour {
+        /// When the hour starts, in the ZIP code's local time.
+        pub time: NaiveDateTime,
+        pub index: i32,
+    }
+
+    /// Today's hourly UV index, and its peak.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Uv {
+        pub peak: i32,
+        /// In order, from the first hour of the forecast.
+        #[serde(default)]
+        pub hours: Vec<UvHour>,
+        /// The location's time zone, which the hours are in.
+        pub timezone: Tz,
+    }
+
+    /// Parse the EPA's `DATE_TIME`, like `OCT/18/2026 07 AM`.
+    fn parse_hour(date_time: &str) -> Result<NaiveDateTime, UvError> {
+        NaiveDateTime::parse_from_str(&format!("{} 00", date_time), "%b/%d/%Y %I %p %M")
+            .map_err(|_| UvError::Time(String::from(date_time)))
+    }
+
+    impl Uv {
+        /// The peak, if it's high enough to mention.
+        pub fn text(&self) -> Option<TextWidget> {
+            let uv_color = match self.peak {
+                0..=4 => "good",
+                5..=9 => "warn",
+                _ => "bad",
+            };
+            (self.peak >= TEXT_FROM).then(|| TextWidget {
+                text: format!("{} UV", self.peak),
                 color: String::from(uv_color),
+                font: FontName::default(),
+                stale: false,
             })
         }
+
+        /// The hours from the one `now` is in to the end of the forecast.
+        pub fn remaining(&self, now: NaiveDateTime) -> &[UvHour] {
+            let start = self
+                .hours
+                .iter()
+                .position(|hour| hour.time + Duration::hours(1) > now)
+                .unwrap_or(self.hours.len());
+            &self.hours[start..]
+        }
+
+        /// The remaining hours as bars in the EPA's colors, with a row
+        /// of marks along the top: the current hour in the text color,
+        /// and the hours at or above `threshold` in the accent color.
+        /// The EPA's colors are what the scale is printed in everywhere,
+        /// so unlike the marks they don't follow the theme. `None` once
+        /// the forecast is over.
+        pub fn chart(&self, now: DateTime<Utc>, threshold: i32) -> Option<Chart> {
+            // The hours are in the location's time, which
+            // needn't be the time where this is running.
+            let now = now.with_timezone(&self.timezone).naive_local();
+            let hours = self.remaining(now);
+            if hours.is_empty() {
+                return None;
+            }
+            let stop = |at: f64, color: &str| Stop {
+                at,
+                color: String::from(color),
+            };
+            let marks = |mark: &dyn Fn(usize, &UvHour) -> bool, color: &str| {
+                Series::sparse(
+                    hours
+                        .iter()
+                        .enumerate()
+                        .map(|(i, hour)| mark(i, hour).then_some(1.0))
+                        .collect(),
+                )
+                .set_kind(ChartKind::Dot)
+                .set_range(Range {
+                    min: Some(0.0),
+                    max: Some(1.0),
+                })
+                .set_color(String::from(color))
+            };
+            Some(
+                Chart::new(
+                    Series::new(hours.iter().map(|hour| hour.index as f64).collect())
+                        .set_range(Range {
+                            min: Some(0.0),
+                            max: Some(CHART_MAX),
+                        })
+                        .set_ramp(Ramp {
+                            stops: vec![
+                                stop(0.0, "#299501"),
+                                stop(3.0, "#f7e401"),
+                                stop(6.0, "#f95901"),
+                                stop(8.0, "#d90011"),
+                                stop(11.0, "#6c49cb"),
+                            ],
+                            smooth: false,
+                        }),
+                )
+                .add_series(marks(&|_, hour| hour.index >= threshold, "accent"))
+                .add_series(marks(&|i, hour| i == 0 && hour.time <= now, "text")),
+            )
+        }
+    }
+
+    #[derive(Debug, Error)]
+    pub enum UvError {
+        #[error("could not get the UV forecast: {0}")]
+        Request(#[from] reqwest::Error),
+        #[error("the UV forecast has an invalid time `{0}`")]
+        Time(String),
+        #[error("the UV forecast is empty")]
+        NoForecast,
+        #[error("the UV forecast needs a ZIP code in the location")]
+        NoZip,
+        #[error(transparent)]
+        Location(#[from] LocationError),
+    }
+
+    /// The EPA's hourly UV forecast for the location's ZIP code.
+    pub struct UvSource {
+        pub resolver: Arc<Resolver>,
+    }
+
+    #[async_trait]
+    impl DataSource for UvSource {
+        type Output = Uv;
+        type Error = UvError;
+
+        const NAME: &'static str = "uv";
+
+        fn refresh_interval(&self) -> Duration {
+            Duration::hours(1)
+        }
+
+        async fn fetch(&self) -> Result<Reading<Uv>, UvError> {
+            // https://www.epa.gov/enviro/web-services#uvindex
+            let zip = self
+                .resolver
+                .location()
+                .zip
+                .as_ref()
+                .ok_or(UvError::NoZip)?;
+            let resp: Vec<Data> = reqwest::Client::new()
+                .get(format!(
+                    "https://data.epa.gov/efservice/getEnvirofactsUVHOURLY/ZIP/{}/json",
+                    zip
+                ))
+                .header(USER_AGENT, "tidbyt")
+                .send()
+                .await?
+                .json::<Vec<Data>>()
+                .await?;
+
+            let mut hours = resp
+                .iter()
+                .map(|d| {
+                    Ok(UvHour {
+                        time: parse_hour(&d.DATE_TIME)?,
+                        index: d.UV_VALUE,
+                    })
+                })
+                .collect::<Result<Vec<_>, UvError>>()?;
+            hours.sort_by_key(|hour| hour.time);
+            let peak = hours
+                .iter()
+                .map(|hour| hour.index)
+                .max()
+                .ok_or(UvError::NoForecast)?;
+            let timezone = self.resolver.timezone().await?;
+
+            Ok(Reading::Shown(Uv {
+                peak,
+                hours,
+                timezone,
+            }))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::{NaiveDate, TimeZone};
+        use raqote::{DrawTarget, Point};
+
+        fn at(hour: u32, minute: u32, second: u32) -> NaiveDateTime {
+            NaiveDate::from_ymd_opt(2026, 10, 18)
+                .unwrap()
+                .and_hms_opt(hour, minute, second)
+                .unwrap()
+        }
+
+        /// From 7 AM to 7 PM, peaking at 7 around 1 PM.
+        fn uv(timezone: Tz) -> Uv {
+            Uv {
+                peak: 7,
+                hours: [0, 1, 2, 4, 5, 6, 7, 7, 6, 4, 3, 1, 0]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, index)| UvHour {
+                        time: at(7 + i as u32, 0, 0),
+                        index: *index,
+                    })
+                    .collect(),
+                timezone,
+            }
+        }
+
+        fn utc(hour: u32, minute: u32) -> DateTime<Utc> {
+            Utc.from_utc_datetime(&at(hour, minute, 0))
+        }
+
+        /// The marks along the chart's top row: `t` for the
+        /// current hour, `a` for the hours over the threshold.
+        fn marks(chart: &Chart) -> String {
+            let width = chart.width() as i32;
+            let mut dt = DrawTarget::new(width, chart.height() as i32);
+            chart.render(&mut dt, Point::new(0., 0.)).unwrap();
+            dt.get_data()[..width as usize]
+                .iter()
+                .map(|pixel| match pixel {
+                    0xffffffff => 't',
+                    0xff00ffff => 'a',
+                    0 => '.',
+                    _ => '?',
+                })
+                .collect()
+        }
+
+        #[test]
+        fn parses_the_epas_times() {
+            assert_eq!(parse_hour("OCT/18/2026 07 AM").unwrap(), at(7, 0, 0));
+            assert_eq!(parse_hour("OCT/18/2026 12 PM").unwrap(), at(12, 0, 0));
+            assert_eq!(parse_hour("OCT/18/2026 12 AM").unwrap(), at(0, 0, 0));
+            assert_eq!(parse_hour("OCT/18/2026 07 PM").unwrap(), at(19, 0, 0));
+            assert!(matches!(
+                parse_hour("2026-10-18 07:00"),
+                Err(UvError::Time(time)) if time == "2026-10-18 07:00"
+            ));
+        }
+
+        #[test]
+        fn the_current_hour_is_the_first_remaining() {
+            let uv = uv(Tz::UTC);
+            assert_eq!(uv.remaining(at(5, 0, 0)).len(), 13);
+            assert_eq!(uv.remaining(at(7, 0, 0)).len(), 13);
+            assert_eq!(uv.remaining(at(7, 59, 59)).len(), 13);
+            assert_eq!(uv.remaining(at(8, 0, 0)).len(), 12);
+            assert_eq!(uv.remaining(at(8, 0, 0))[0].time, at(8, 0, 0));
+            assert_eq!(uv.remaining(at(19, 59, 59)).len(), 1);
+        }
+
+        #[test]
+        fn nothing_remains_after_the_last_hour() {
+            let uv = uv(Tz::UTC);
+            assert!(uv.remaining(at(20, 0, 0)).is_empty());
+            assert!(uv.chart(utc(20, 0), 3).is_none());
+        }
+
+        #[test]
+        fn hours_over_the_threshold_are_marked() {
+            let uv = uv(Tz::UTC);
+            // Before the forecast starts, so there's no current hour.
+            assert_eq!(marks(&uv.chart(utc(5, 0), 5).unwrap()), "....aaaaa....");
+            assert_eq!(marks(&uv.chart(utc(5, 0), 3).unwrap()), "...aaaaaaaa..");
+            assert_eq!(marks(&uv.chart(utc(5, 0), 11).unwrap()), ".............");
+        }
+
+        #[test]
+        fn the_current_hour_is_marked_once_it_starts() {
+            let uv = uv(Tz::UTC);
+            assert_eq!(marks(&uv.chart(utc(6, 59), 5).unwrap()), "....aaaaa....");
+            assert_eq!(marks(&uv.chart(utc(7, 0), 5).unwrap()), "t...aaaaa....");
+            // Over the threshold, the current hour's mark wins.
+            assert_eq!(marks(&uv.chart(utc(11, 30), 5).unwrap()), "taaaa....");
+        }
+
+        #[test]
+        fn hours_are_in_the_locations_time() {
+            let uv = uv(Tz::America__New_York);
+            // 7:30 in New York, but 3:30 in UTC.
+            let chart = uv.chart(utc(11, 30), 5).unwrap();
+            assert_eq!(chart.width(), 13);
+            assert_eq!(marks(&chart), "t...aaaaa....");
+            // 3:30 in New York, before the forecast.
+            assert_eq!(marks(&uv.chart(utc(7, 30), 5).unwrap()), "....aaaaa....");
+            // 8 PM in New York, after it.
+            assert!(uv.chart(utc(0, 0) + Duration::days(1), 5).is_none());
+        }
     }
 }
@@ -1,9 +1,17 @@
 pub mod weather {
-    use anyhow::Result;
-    use cached::proc_macro::cached;
+    use async_trait::async_trait;
+    use chrono::{DateTime, Duration, NaiveDate};
     use reqwest::header::USER_AGENT;
-    use serde::Deserialize;
+    use serde::{Deserialize, Serialize};
+    use std::sync::{Arc, Mutex};
+    use thiserror::Error;
 
+    use crate::chart::chart::{Chart, ChartKind, Ramp, Range, Series, Stop};
+    use crate::font::font::FontName;
+    use crate::icon::icon::Icon;
+    use crate::locale::locale::Units;
+    use crate::location::location::{LocationError, Resolver};
+    use crate::source::source::{DataSource, Reading};
     use crate::TextWidget;
 
     #[derive(Debug, Deserialize)]
@@ -15,7 +23,7 @@ pub mod weather {
     #[derive(Debug, Deserialize)]
     struct ProbabilityOfPrecipitation {
         unitCode: String,
-        value: i32,
+        value: Option<i32>,
     }
 
     // TODO: remove all of the fields we won't use.
@@ -75,20 +83,417 @@ pub mod weather {
         properties: Properties,
     }
 
-    // TODO: generate more than a string for weather.
-    #[cached(time = 120, result = true)]
-    pub async fn get_weather() -> Result<TextWidget> {
-        let resp: Feature = reqwest::Client::new()
-            .get("https://api.weather.gov/gridpoints/OKX/33,33/forecast/hourly")
-            .header(USER_AGENT, "tidbyt")
-            .send()
-            .await?
-            .json::<Feature>()
-            .await?;
-        let temp = resp.properties.periods[0].temperature.to_string();
-        Ok(TextWidget {
-            text: format!("{}°", temp),
-            color: String::from("#fff"),
-        })
+    /// How many hours ahead to look for precipitation.
+    const PRECIPITATION_HOURS: usize = 3;
+
+    /// The chance of precipitation, in percent, at which it's worth
+    /// showing. The NWS calls 60% and up "likely".
+    const PRECIPITATION_LIKELY: i32 = 60;
+
+    /// How many hours of the hourly forecast to keep for charts.
+    pub const FORECAST_HOURS: usize = 24;
+
+    /// Temperatures at or below this are charted as freezing, in °F.
+    const FREEZING: i32 = 32;
+
+    /// What the sky is doing, simplified down to what we have icons for.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Condition {
+        Clear,
+        PartlyCloudy,
+        Cloudy,
+        Rain,
+        Snow,
+        Thunder,
+    }
+
+    impl Condition {
+        /// Read the condition from an NWS icon URL, like
+        /// `https://api.weather.gov/icons/land/day/rain,40/tsra,60?size=small`.
+        /// When the forecast changes during the period, there are two
+        /// codes, and the first is for the start of it.
+        fn from_icon(url: &str) -> Condition {
+            let path = url.split('?').next().unwrap_or_default();
+            let code = path
+                .split('/')
+                .skip_while(|segment| *segment != "day" && *segment != "night")
+                .nth(1)
+                .and_then(|segment| segment.split(',').next())
+                .unwrap_or_default();
+            match code {
+                "skc" | "few" | "wind_skc" | "wind_few" | "hot" | "cold" => Condition::Clear,
+                "sct" | "wind_sct" => Condition::PartlyCloudy,
+                "tsra" | "tsra_sct" | "tsra_hi" | "tornado" | "hurricane" | "tropical_storm" => {
+                    Condition::Thunder
+                }
+                "snow" | "blizzard" | "sleet" | "rain_snow" | "snow_sleet" | "snow_fzra" => {
+                    Condition::Snow
+                }
+                "rain" | "rain_showers" | "rain_showers_hi" | "rain_sleet" | "fzra"
+                | "rain_fzra" => Condition::Rain,
+                // Overcast, fog, smoke, haze and anything new.
+                _ => Condition::Cloudy,
+            }
+        }
+    }
+
+    /// One hour of the hourly forecast.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Hour {
+        /// In °F, like everything from the NWS.
+        pub temperature: i32,
+        /// The chance of precipitation, in percent.
+        pub precipitation: i32,
+    }
+
+    /// The current hourly forecast, and what's coming up today.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Weather {
+        /// In °F, like everything from the NWS.
+        pub temperature: i32,
+        pub condition: Condition,
+        pub daytime: bool,
+        /// The highest and lowest temperatures today, from earlier
+        /// fetches as well as the hours that are left.
+        pub high: i32,
+        pub low: i32,
+        /// The highest chance of precipitation over the
+        /// next few hours, in percent.
+        pub precipitation: i32,
+        /// The next day of the hourly forecast, starting now.
+        pub hourly: Vec<Hour>,
+    }
+
+    impl Weather {
+        pub fn icon(&self) -> Icon {
+            match (self.condition, self.daytime) {
+                (Condition::Clear, true) => Icon::Sun,
+                (Condition::Clear, false) => Icon::Moon,
+                (Condition::PartlyCloudy, true) => Icon::SunAndCloud,
+                (Condition::PartlyCloudy, false) => Icon::MoonAndCloud,
+                (Condition::Cloudy, _) => Icon::Cloud,
+                (Condition::Rain, _) => Icon::Rain,
+                (Condition::Snow, _) => Icon::Snow,
+                (Condition::Thunder, _) => Icon::Thunder,
+            }
+        }
+
+        pub fn precipitation_likely(&self) -> bool {
+            self.precipitation >= PRECIPITATION_LIKELY
+        }
+
+        /// A sparkline of the next `hours` hours, with the temperature
+        /// as bars scaled between its low and high, colored by whether
+        /// it's freezing, and the chance of precipitation over them.
+        pub fn forecast_chart(&self, hours: usize, units: Units) -> Chart {
+            let hourly = &self.hourly[..hours.min(self.hourly.len())];
+            let temperature = |hour: &Hour| units.temperature(hour.temperature);
+            let temperatures = hourly.iter().map(|hour| temperature(hour) as f64).collect();
+            let low = hourly.iter().map(temperature).min();
+            let precipitation = hourly
+                .iter()
+                .map(|hour| (hour.precipitation > 0).then_some(hour.precipitation as f64))
+                .collect();
+            let stop = |at: i32, color: &str| Stop {
+                at: at as f64,
+                color: String::from(color),
+            };
+            let freezing = units.temperature(FREEZING);
+            Chart::new(
+                Series::new(temperatures)
+                    .set_range(Range {
+                        min: low.map(|low| (low - 1) as f64),
+                        max: None,
+                    })
+                    .set_ramp(Ramp {
+                        stops: vec![stop(freezing, "cold"), stop(freezing + 1, "warm")],
+                        smooth: false,
+                    }),
+            )
+            .add_series(
+                Series::sparse(precipitation)
+                    .set_kind(ChartKind::Dot)
+                    .set_range(Range {
+                        min: Some(0.0),
+                        max: Some(100.0),
+                    })
+                    .set_color(String::from("precip")),
+            )
+        }
+
+        pub fn text(&self, units: Units) -> TextWidget {
+            TextWidget {
+                text: format!("{}°", units.temperature(self.temperature)),
+                color: String::from("text"),
+                font: FontName::default(),
+                stale: false,
+            }
+        }
+    }
+
+    #[derive(Debug, Error)]
+    pub enum WeatherError {
+        #[error("could not get the forecast: {0}")]
+        Request(#[from] reqwest::Error),
+        #[error("the forecast has no periods")]
+        NoPeriods,
+        #[error(transparent)]
+        Location(#[from] LocationError),
+    }
+
+    /// The highest and lowest temperatures seen on a day.
+    #[derive(Debug, Clone, Copy)]
+    struct DayRange {
+        day: NaiveDate,
+        high: i32,
+        low: i32,
+    }
+
+    impl DayRange {
+        /// `range` widened by `high` and `low`, or a new range
+        /// if it's from another day.
+        fn widen(range: Option<DayRange>, day: NaiveDate, high: i32, low: i32) -> DayRange {
+            match range {
+                Some(range) if range.day == day => DayRange {
+                    day,
+                    high: range.high.max(high),
+                    low: range.low.min(low),
+                },
+                _ => DayRange { day, high, low },
+            }
+        }
+    }
+
+    /// The hourly forecast from NOAA, for the gridpoint covering the location.
+    pub struct WeatherSource {
+        pub resolver: Arc<Resolver>,
+        /// Today's temperatures from earlier fetches. The hourly
+        /// forecast only has the hours that are left, so without
+        /// these the high and low would shrink to the current
+        /// temperature by the end of the day.
+        seen: Mutex<Option<DayRange>>,
+    }
+
+    impl WeatherSource {
+        pub fn new(resolver: Arc<Resolver>) -> WeatherSource {
+            WeatherSource {
+                resolver,
+                seen: Mutex::new(None),
+            }
+        }
+
+        /// Widen the day's range by `high` and `low`, and return the result.
+        fn remember(&self, day: NaiveDate, high: i32, low: i32) -> (i32, i32) {
+            let mut seen = self.seen.lock().unwrap_or_else(|err| err.into_inner());
+            let range = DayRange::widen(*seen, day, high, low);
+            *seen = Some(range);
+            (range.high, range.low)
+        }
+    }
+
+    #[async_trait]
+    impl DataSource for WeatherSource {
+        type Output = Weather;
+        type Error = WeatherError;
+
+        const NAME: &'static str = "weather";
+
+        fn refresh_interval(&self) -> Duration {
+            Duration::minutes(2)
+        }
+
+        async fn fetch(&self) -> Result<Reading<Weather>, WeatherError> {
+            let gridpoint = self.resolver.gridpoint().await?;
+            let resp: Feature = reqwest::Client::new()
+                .get(format!(
+                    "https://api.weather.gov/gridpoints/{}/forecast/hourly",
+                    gridpoint
+                ))
+                .header(USER_AGENT, "tidbyt")
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Feature>()
+                .await?;
+            let periods = &resp.properties.periods;
+            let period = periods.first().ok_or(WeatherError::NoPeriods)?;
+            // Periods have the forecast office's offset, so
+            // their dates are local to the forecast.
+            let day = |period: &Period| {
+                DateTime::parse_from_rfc3339(&period.startTime)
+                    .ok()
+                    .map(|start| start.date_naive())
+            };
+            let today: Vec<i32> = periods
+                .iter()
+                .take_while(|p| day(p) == day(period))
+                .map(|p| p.temperature)
+                .collect();
+            let mut high = today.iter().copied().max().unwrap_or(period.temperature);
+            let mut low = today.iter().copied().min().unwrap_or(period.temperature);
+            if let Some(day) = day(period) {
+                (high, low) = self.remember(day, high, low);
+            }
+            let precipitation = periods
+                .iter()
+                .take(PRECIPITATION_HOURS)
+                .filter_map(|p| p.probabilityOfPrecipitation.value)
+                .max()
+                .unwrap_or(0);
+            Ok(Reading::Shown(Weather {
+                temperature: period.temperature,
+                condition: Condition::from_icon(&period.icon),
+                daytime: period.isDaytime,
+                high,
+                low,
+                precipitation,
+                hourly: periods
+                    .iter()
+                    .take(FORECAST_HOURS)
+                    .map(|p| Hour {
+                        temperature: p.temperature,
+                        precipitation: p.probabilityOfPrecipitation.value.unwrap_or(0),
+                    })
+                    .collect(),
+            }))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn the_days_range_keeps_earlier_temperatures() {
+            let day = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+            // The afternoon's forecast, then late evening's,
+            // when only the last cool hours are left.
+            let range = DayRange::widen(None, day, 75, 61);
+            let range = DayRange::widen(Some(range), day, 63, 60);
+            assert_eq!((range.high, range.low), (75, 60));
+        }
+
+        #[test]
+        fn the_days_range_starts_over_on_a_new_day() {
+            let day = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+            let range = DayRange::widen(None, day, 75, 61);
+            let range = DayRange::widen(Some(range), day.succ_opt().unwrap(), 68, 55);
+            assert_eq!((range.high, range.low), (68, 55));
+        }
+
+        #[test]
+        fn conditions_come_from_the_icon_code() {
+            let icons = [
+                (
+                    "https://api.weather.gov/icons/land/day/skc?size=small",
+                    Condition::Clear,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/night/few?size=small",
+                    Condition::Clear,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/day/wind_few?size=small",
+                    Condition::Clear,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/day/hot?size=small",
+                    Condition::Clear,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/day/sct?size=small",
+                    Condition::PartlyCloudy,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/night/wind_sct?size=small",
+                    Condition::PartlyCloudy,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/day/bkn?size=small",
+                    Condition::Cloudy,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/night/ovc?size=small",
+                    Condition::Cloudy,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/day/fog?size=small",
+                    Condition::Cloudy,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/day/haze?size=small",
+                    Condition::Cloudy,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/day/rain,40?size=small",
+                    Condition::Rain,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/night/rain_showers,20?size=small",
+                    Condition::Rain,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/day/fzra,60?size=small",
+                    Condition::Rain,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/night/snow,80?size=small",
+                    Condition::Snow,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/day/rain_snow,50?size=small",
+                    Condition::Snow,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/day/tsra,30?size=small",
+                    Condition::Thunder,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/night/tsra_hi,20?size=small",
+                    Condition::Thunder,
+                ),
+            ];
+            for (url, condition) in icons {
+                assert_eq!(Condition::from_icon(url), condition, "{}", url);
+            }
+        }
+
+        #[test]
+        fn changing_forecasts_use_the_first_condition() {
+            let icons = [
+                (
+                    "https://api.weather.gov/icons/land/day/rain,40/tsra,60?size=small",
+                    Condition::Rain,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/night/tsra,60/sct?size=small",
+                    Condition::Thunder,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/day/sct/rain_showers,30?size=medium",
+                    Condition::PartlyCloudy,
+                ),
+                (
+                    "https://api.weather.gov/icons/land/night/snow,70/ovc?size=small",
+                    Condition::Snow,
+                ),
+            ];
+            for (url, condition) in icons {
+                assert_eq!(Condition::from_icon(url), condition, "{}", url);
+            }
+        }
+
+        #[test]
+        fn unknown_icons_are_cloudy() {
+            for url in [
+                "",
+                "https://api.weather.gov/icons/land/day/?size=small",
+                "https://api.weather.gov/icons/land/day/smoke?size=small",
+                "https://example.com/rain.png",
+            ] {
+                assert_eq!(Condition::from_icon(url), Condition::Cloudy, "{}", url);
+            }
+        }
     }
 }